@@ -1,5 +1,5 @@
 use home::home_dir;
-use libc::{tcgetattr, tcsetattr, termios, ECHO, ICANON, STDIN_FILENO, TCSANOW};
+use libc::{STDERR_FILENO, STDIN_FILENO};
 use std::env;
 use std::fs;
 use std::io;
@@ -8,28 +8,105 @@ use std::process;
 
 mod shell;
 
-static BUILTINS: [&str; 5] = ["cd", "pwd", "exit", "echo", "type"];
+use shell::Input;
 
-fn main() {
-    let mut termios = unsafe { std::mem::zeroed::<termios>() };
-    if unsafe { tcgetattr(STDIN_FILENO, &mut termios) } != 0 {
-        eprintln!("sh: fatal error: {}", io::Error::last_os_error());
-        process::exit(1);
-    }
+static BUILTINS: [&str; 19] = [
+    "cd", "pwd", "exit", "echo", "type", "break", "continue", "return", "export", "unset", "shopt",
+    "set", "history", "complete", "compgen", "jobs", "fg", "bg", "disown",
+];
+
+struct Options {
+    command: Option<String>,
+    interactive: bool,
+    stdin: bool,
+    operands: Vec<String>,
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: None,
+        interactive: false,
+        stdin: false,
+        operands: vec![],
+    };
+    let mut read_command = false;
 
-    termios.c_lflag &= !(ICANON | ECHO);
+    for arg in args.by_ref() {
+        if arg == "--" || arg == "-" {
+            break;
+        }
+
+        if !arg.starts_with('-') || arg.len() == 1 {
+            options.operands.push(arg);
+            break;
+        }
 
-    if unsafe { tcsetattr(STDIN_FILENO, TCSANOW, &termios) } != 0 {
-        eprintln!("sh: fatal error: {}", io::Error::last_os_error());
-        process::exit(1);
+        for flag in arg.chars().skip(1) {
+            match flag {
+                'c' => read_command = true,
+                'i' => options.interactive = true,
+                's' => options.stdin = true,
+                _ => return Err(format!("-{}: invalid option", flag)),
+            }
+        }
     }
 
-    let mut shell = shell::new();
+    options.operands.extend(args);
 
-    loop {
-        shell.prompt();
-        shell.process();
+    if read_command {
+        if options.operands.is_empty() {
+            return Err(String::from("-c: option requires an argument"));
+        }
+        options.command = Some(options.operands.remove(0));
     }
+
+    Ok(options)
+}
+
+fn main() {
+    let mut args = env::args();
+    let name = args.next().unwrap_or_else(|| String::from("sh"));
+
+    let mut options = match parse_options(args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("sh: {}", error);
+            eprintln!("usage: sh [-is] [-c command [name [arg ...]]] [script [arg ...]]");
+            process::exit(2);
+        }
+    };
+
+    let input = if let Some(command) = options.command.take() {
+        if options.operands.is_empty() {
+            options.operands.push(name);
+        }
+        Input::text(&command)
+    } else if !options.stdin && !options.operands.is_empty() {
+        match fs::read_to_string(&options.operands[0]) {
+            Ok(script) => Input::text(&script),
+            Err(error) => {
+                eprintln!("sh: {}: {}", options.operands[0], error);
+                process::exit(if error.kind() == io::ErrorKind::NotFound {
+                    127
+                } else {
+                    126
+                });
+            }
+        }
+    } else {
+        options.operands.insert(0, name);
+        if options.interactive
+            || unsafe { libc::isatty(STDIN_FILENO) == 1 && libc::isatty(STDERR_FILENO) == 1 }
+        {
+            Input::Terminal
+        } else {
+            Input::Stdin
+        }
+    };
+
+    let mut shell = shell::new(input, options.operands);
+    let status = shell.run();
+    shell.exit(status);
 }
 
 fn resolve_path(path: &str) -> PathBuf {
@@ -49,7 +126,10 @@ fn resolve_path(path: &str) -> PathBuf {
         return path.to_path_buf();
     }
 
-    std::env::current_dir().unwrap().join(path)
+    match env::current_dir() {
+        Ok(dir) => dir.join(path),
+        Err(_) => path.to_path_buf(),
+    }
 }
 
 fn is_executable(path: &Path) -> bool {
@@ -59,17 +139,20 @@ fn is_executable(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-fn find_executable(target: &str) -> Option<String> {
-    env::var_os("PATH").and_then(|paths| {
-        env::split_paths(&paths).find_map(|path| {
-            let full_path = path.join(target);
-            if full_path.exists() && is_executable(&full_path) {
-                Some(full_path.to_string_lossy().into_owned())
-            } else {
-                None
-            }
+fn find_command(target: &str) -> Option<PathBuf> {
+    let paths: Vec<PathBuf> = env::var_os("PATH")
+        .map(|paths| {
+            env::split_paths(&paths)
+                .map(|path| path.join(target))
+                .collect()
         })
-    })
+        .unwrap_or_default();
+
+    paths
+        .iter()
+        .find(|path| path.is_file() && is_executable(path))
+        .or_else(|| paths.iter().find(|path| path.is_file()))
+        .cloned()
 }
 
 fn load_executable() -> Vec<[String; 2]> {
@@ -95,21 +178,173 @@ fn load_executable() -> Vec<[String; 2]> {
     results
 }
 
-fn get_completions<'a>(commands: &'a [[String; 2]], prefix: &str) -> Vec<&'a str> {
+fn matches_prefix(text: &str, prefix: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        text.to_lowercase().starts_with(&prefix.to_lowercase())
+    } else {
+        text.starts_with(prefix)
+    }
+}
+
+fn get_completions<'a>(
+    commands: &'a [[String; 2]],
+    prefix: &str,
+    ignore_case: bool,
+) -> Vec<&'a str> {
     let mut results: Vec<&str> = BUILTINS
         .iter()
-        .filter(|cmd| cmd.starts_with(prefix))
+        .filter(|cmd| matches_prefix(cmd, prefix, ignore_case))
         .cloned()
         .collect();
 
     let mut system_results = commands
         .iter()
-        .filter(|cmd| cmd[0].starts_with(prefix))
+        .filter(|cmd| matches_prefix(&cmd[0], prefix, ignore_case))
         .map(|cmd| cmd[0].as_str())
         .collect();
 
     results.append(&mut system_results);
     results.sort();
+    results.dedup();
     results.sort_by_key(|s| s.len());
     results
 }
+
+fn get_path_completions(prefix: &str, executables: bool, ignore_case: bool) -> Vec<String> {
+    if prefix == "~" {
+        return vec![String::from("~/")];
+    }
+
+    let (dir, name) = match prefix.rfind('/') {
+        Some(index) => (&prefix[..=index], &prefix[index + 1..]),
+        None => ("", prefix),
+    };
+
+    let Ok(entries) = fs::read_dir(resolve_path(if dir.is_empty() { "." } else { dir })) else {
+        return vec![];
+    };
+
+    let mut results: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+
+            if !matches_prefix(&file_name, name, ignore_case)
+                || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+
+            let path = entry.path();
+
+            if path.is_dir() {
+                Some(format!("{}{}/", dir, file_name))
+            } else if !executables || is_executable(&path) {
+                Some(format!("{}{}", dir, file_name))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    results.sort();
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        parse_options(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn parses_command_line_options() {
+        let parsed = options(&["-c", "echo $0", "name", "a"]).unwrap();
+        assert_eq!(parsed.command.as_deref(), Some("echo $0"));
+        assert_eq!(parsed.operands, vec!["name", "a"]);
+
+        let parsed = options(&["-is", "a", "-c"]).unwrap();
+        assert!(parsed.interactive && parsed.stdin);
+        assert_eq!(parsed.command, None);
+        assert_eq!(parsed.operands, vec!["a", "-c"]);
+
+        let parsed = options(&["--", "-x"]).unwrap();
+        assert_eq!(parsed.operands, vec!["-x"]);
+
+        assert!(options(&["-x"]).is_err());
+        assert!(options(&["-c"]).is_err());
+    }
+
+    #[test]
+    fn completes_paths() {
+        assert_eq!(get_path_completions("~", false, false), vec!["~/"]);
+        assert_eq!(
+            get_path_completions("Cargo.t", false, false),
+            vec!["Cargo.toml"]
+        );
+        assert_eq!(
+            get_path_completions("cargo.t", false, true),
+            vec!["Cargo.toml"]
+        );
+        assert!(get_path_completions("sr", false, false).contains(&String::from("src/")));
+        assert_eq!(
+            get_path_completions("src/ma", false, false),
+            vec!["src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn completes_paths_in_a_removed_directory() {
+        let dir = env::temp_dir().join(format!("simple-shell-test-{}-gone", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let pid = unsafe { libc::fork() };
+
+        if pid == 0 {
+            let completed = env::set_current_dir(&dir).is_ok()
+                && fs::remove_dir(&dir).is_ok()
+                && std::panic::catch_unwind(|| {
+                    get_path_completions("a", false, false).is_empty()
+                        && resolve_path("a") == Path::new("a")
+                })
+                .unwrap_or(false);
+
+            unsafe { libc::_exit(if completed { 0 } else { 1 }) };
+        }
+
+        let mut status = 0;
+        unsafe { libc::waitpid(pid, &mut status, 0) };
+        assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn completes_commands_by_prefix() {
+        let commands = vec![
+            [String::from("cargo"), String::from("/bin/cargo")],
+            [String::from("cat"), String::from("/bin/cat")],
+            [String::from("cat"), String::from("/usr/bin/cat")],
+            [String::from("Cal"), String::from("/bin/Cal")],
+        ];
+
+        assert_eq!(
+            get_completions(&commands, "ca", false),
+            vec!["cat", "cargo"]
+        );
+        assert_eq!(
+            get_completions(&commands, "CA", true),
+            vec!["Cal", "cat", "cargo"]
+        );
+    }
+
+    #[test]
+    fn finds_commands_only_on_path() {
+        assert!(Path::new("Cargo.toml").exists());
+        assert_eq!(find_command("Cargo.toml"), None);
+        assert_eq!(find_command("src"), None);
+
+        let sh = find_command("sh").unwrap();
+        assert!(sh.is_absolute() && is_executable(&sh));
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct List {
+    pub items: Vec<AndOr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+    pub background: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub bang: bool,
+    pub commands: Vec<Command>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Compound(CompoundCommand, Vec<Redirect>),
+    Function(String, Rc<Command>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    pub assignments: Vec<(String, Word)>,
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompoundCommand {
+    Group(List),
+    Subshell(List),
+    If {
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    Loop {
+        until: bool,
+        condition: List,
+        body: List,
+    },
+    For {
+        name: String,
+        words: Option<Vec<Word>>,
+        body: List,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub fd: Option<i32>,
+    pub kind: RedirectKind,
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    Input,
+    ReadWrite,
+    Output,
+    Append,
+    OutputAll,
+    AppendAll,
+    DupInput,
+    DupOutput,
+    HereString,
+    HereDoc,
+}
+
+impl RedirectKind {
+    pub fn default_fd(&self) -> i32 {
+        match self {
+            RedirectKind::Input
+            | RedirectKind::ReadWrite
+            | RedirectKind::DupInput
+            | RedirectKind::HereString
+            | RedirectKind::HereDoc => 0,
+            RedirectKind::Output
+            | RedirectKind::Append
+            | RedirectKind::OutputAll
+            | RedirectKind::AppendAll
+            | RedirectKind::DupOutput => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    Literal(String),
+    Quoted(String),
+    DoubleQuoted(Vec<WordPart>),
+    Param(Param),
+    CommandSubst(List, String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    pub op: ParamOp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamOp {
+    Plain,
+    Length,
+    Default(bool, Word),
+    Assign(bool, Word),
+    Error(bool, Word),
+    Alternative(bool, Word),
+}
+
+impl Word {
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(literal)] => Some(literal),
+            _ => None,
+        }
+    }
+
+    pub fn is_quoted(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, WordPart::Quoted(_) | WordPart::DoubleQuoted(_)))
+    }
+
+    pub fn unquoted(&self) -> String {
+        fn collect(parts: &[WordPart], result: &mut String) {
+            for part in parts.iter() {
+                match part {
+                    WordPart::Literal(text) | WordPart::Quoted(text) => result.push_str(text),
+                    WordPart::DoubleQuoted(parts) => collect(parts, result),
+                    part => result.push_str(&part.to_string()),
+                }
+            }
+        }
+
+        let mut result = String::new();
+        collect(&self.parts, &mut result);
+        result
+    }
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item)?;
+            if item.background {
+                write!(f, " &")?;
+            } else if index + 1 < self.items.len() {
+                write!(f, ";")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AndOr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+
+        for (connector, pipeline) in self.rest.iter() {
+            match connector {
+                Connector::And => write!(f, " && {}", pipeline)?,
+                Connector::Or => write!(f, " || {}", pipeline)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bang {
+            write!(f, "! ")?;
+        }
+
+        for (index, command) in self.commands.iter().enumerate() {
+            if index > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{}", command)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Simple(simple) => write!(f, "{}", simple),
+            Command::Compound(compound, redirects) => {
+                write!(f, "{}", compound)?;
+                for redirect in redirects.iter() {
+                    write!(f, " {}", redirect)?;
+                }
+                Ok(())
+            }
+            Command::Function(name, body) => write!(f, "{} () {}", name, body),
+        }
+    }
+}
+
+impl fmt::Display for SimpleCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let assignments = self
+            .assignments
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value));
+        let words = self.words.iter().map(Word::to_string);
+        let redirects = self.redirects.iter().map(Redirect::to_string);
+
+        let items: Vec<String> = assignments.chain(words).chain(redirects).collect();
+        write!(f, "{}", items.join(" "))
+    }
+}
+
+impl fmt::Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompoundCommand::Group(list) => write!(f, "{{ {}; }}", list),
+            CompoundCommand::Subshell(list) => write!(f, "({})", list),
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (index, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if index == 0 { "if" } else { "elif" };
+                    write!(f, "{} {}; then {}; ", keyword, condition, body)?;
+                }
+                if let Some(body) = otherwise {
+                    write!(f, "else {}; ", body)?;
+                }
+                write!(f, "fi")
+            }
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            } => {
+                let keyword = if *until { "until" } else { "while" };
+                write!(f, "{} {}; do {}; done", keyword, condition, body)
+            }
+            CompoundCommand::For { name, words, body } => {
+                write!(f, "for {}", name)?;
+                if let Some(words) = words {
+                    write!(f, " in")?;
+                    for word in words.iter() {
+                        write!(f, " {}", word)?;
+                    }
+                }
+                write!(f, "; do {}; done", body)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(fd) = self.fd {
+            write!(f, "{}", fd)?;
+        }
+
+        let operator = match self.kind {
+            RedirectKind::Input => "<",
+            RedirectKind::ReadWrite => "<>",
+            RedirectKind::Output => ">",
+            RedirectKind::Append => ">>",
+            RedirectKind::OutputAll => "&>",
+            RedirectKind::AppendAll => "&>>",
+            RedirectKind::DupInput => "<&",
+            RedirectKind::DupOutput => ">&",
+            RedirectKind::HereString => "<<<",
+            RedirectKind::HereDoc => return write!(f, "<<"),
+        };
+
+        write!(f, "{}{}", operator, self.target)
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in self.parts.iter() {
+            write!(f, "{}", part)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WordPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WordPart::Literal(literal) => write!(f, "{}", literal),
+            WordPart::Quoted(quoted) => write!(f, "'{}'", quoted),
+            WordPart::DoubleQuoted(parts) => {
+                write!(f, "\"")?;
+                for part in parts.iter() {
+                    match part {
+                        WordPart::Quoted(quoted) => write!(f, "\\{}", quoted)?,
+                        part => write!(f, "{}", part)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            WordPart::Param(param) => write!(f, "{}", param),
+            WordPart::CommandSubst(_, text) => write!(f, "$({})", text),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (colon, op, word) = match &self.op {
+            ParamOp::Plain => return write!(f, "${{{}}}", self.name),
+            ParamOp::Length => return write!(f, "${{#{}}}", self.name),
+            ParamOp::Default(colon, word) => (colon, '-', word),
+            ParamOp::Assign(colon, word) => (colon, '=', word),
+            ParamOp::Error(colon, word) => (colon, '?', word),
+            ParamOp::Alternative(colon, word) => (colon, '+', word),
+        };
+
+        let colon = if *colon { ":" } else { "" };
+        write!(f, "${{{}{}{}{}}}", self.name, colon, op, word)
+    }
+}
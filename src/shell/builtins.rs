@@ -0,0 +1,375 @@
+use std::env;
+
+use super::exec::Flow;
+use super::parser::{is_name, RESERVED};
+use super::Shell;
+use crate::{find_command, resolve_path, BUILTINS};
+
+pub static SHOPT_OPTIONS: [&str; 6] = [
+    "dotglob",
+    "failglob",
+    "fuzzycomplete",
+    "globstar",
+    "nocasecomplete",
+    "nullglob",
+];
+
+impl Shell<'_> {
+    pub fn run_builtin(&mut self, command: &str, argv: &[String]) -> i32 {
+        match command {
+            "cd" => {
+                let mut target = "~";
+
+                if !argv.is_empty() {
+                    target = argv.first().unwrap();
+                }
+
+                let path = resolve_path(target);
+
+                if path.is_dir() {
+                    if env::set_current_dir(&path).is_err() {
+                        self.error("cd: Failed to change working directory\n");
+                        return 1;
+                    }
+                } else if path.is_file() {
+                    self.error(format!("cd: {}: Is not a directory\n", target));
+                    return 1;
+                } else {
+                    self.error(format!("cd: {}: No such file or directory\n", target));
+                    return 1;
+                }
+
+                0
+            }
+            "pwd" => match env::current_dir() {
+                Ok(path) => {
+                    self.output(format!("{}\n", path.display()));
+                    0
+                }
+                Err(error) => {
+                    self.error(format!("pwd: {}\n", error));
+                    1
+                }
+            },
+            "exit" => {
+                let status = match argv.first() {
+                    Some(code) => code.parse().unwrap_or_else(|_| {
+                        self.error(format!("exit: {}: numeric argument required\n", code));
+                        2
+                    }),
+                    None => self.status,
+                };
+
+                self.exit(status)
+            }
+            "echo" => {
+                self.output(format!("{}\n", argv.join(" ")));
+                0
+            }
+            "type" => {
+                let mut status = 0;
+
+                for target in argv.iter() {
+                    let result = if RESERVED.contains(&target.as_str()) {
+                        format!("{} {}\n", target, "is a shell keyword")
+                    } else if self.functions.contains_key(target) {
+                        format!("{} {}\n", target, "is a function")
+                    } else if BUILTINS.contains(&target.as_str()) {
+                        format!("{} {}\n", target, "is a shell builtin")
+                    } else {
+                        match find_command(target) {
+                            Some(path) => {
+                                status = 0;
+                                format!("{}\n", path.display())
+                            }
+                            None => {
+                                status = 1;
+                                format!("{}: {}\n", target, "not found")
+                            }
+                        }
+                    };
+
+                    self.output(result);
+                }
+
+                status
+            }
+            "break" | "continue" => {
+                if self.loop_depth == 0 {
+                    self.error(format!(
+                        "{}: only meaningful in a `for', `while', or `until' loop\n",
+                        command
+                    ));
+                    return 0;
+                }
+
+                let count = match argv.first().map(|arg| arg.parse::<usize>()) {
+                    None => 1,
+                    Some(Ok(count)) if count > 0 => count.min(self.loop_depth),
+                    Some(_) => {
+                        self.error(format!(
+                            "{}: {}: loop count out of range\n",
+                            command, argv[0]
+                        ));
+                        return 1;
+                    }
+                };
+
+                self.flow = Some(if command == "break" {
+                    Flow::Break(count)
+                } else {
+                    Flow::Continue(count)
+                });
+
+                0
+            }
+            "return" => {
+                if self.function_depth == 0 {
+                    self.error("return: can only `return' from a function\n");
+                    return 1;
+                }
+
+                self.flow = Some(Flow::Return);
+
+                match argv.first() {
+                    Some(code) => code.parse().unwrap_or_else(|_| {
+                        self.error(format!("return: {}: numeric argument required\n", code));
+                        2
+                    }),
+                    None => 0,
+                }
+            }
+            "export" => self.export(argv),
+            "shopt" => self.shopt_builtin(argv),
+            "set" => self.set(argv),
+            "history" => self.history_builtin(argv),
+            "complete" => self.complete(argv),
+            "compgen" => self.compgen(argv),
+            "jobs" => self.jobs_builtin(argv),
+            "fg" | "bg" => self.resume_job(command, argv),
+            "disown" => self.disown(argv),
+            "unset" => {
+                let mut functions = false;
+                let mut names = argv;
+
+                match names.first().map(String::as_str) {
+                    Some("-f") => {
+                        functions = true;
+                        names = &names[1..];
+                    }
+                    Some("-v") => names = &names[1..],
+                    _ => {}
+                }
+
+                for name in names.iter() {
+                    if functions {
+                        self.functions.remove(name);
+                    } else if is_name(name) {
+                        self.unset_var(name);
+                    } else {
+                        self.error(format!("unset: `{}': not a valid identifier\n", name));
+                        return 1;
+                    }
+                }
+
+                0
+            }
+            _ => 1,
+        }
+    }
+
+    fn export(&mut self, argv: &[String]) -> i32 {
+        let mut unexport = false;
+        let mut names = argv;
+
+        while let Some(option) = names.first().filter(|arg| arg.starts_with('-')) {
+            match option.as_str() {
+                "-n" => unexport = true,
+                "-p" => {}
+                _ => {
+                    self.error(format!("export: {}: invalid option\n", option));
+                    return 2;
+                }
+            }
+            names = &names[1..];
+        }
+
+        if names.is_empty() {
+            let mut vars: Vec<(String, Option<String>)> = env::vars()
+                .map(|(name, value)| (name, Some(value)))
+                .chain(self.exported.iter().map(|name| (name.clone(), None)))
+                .collect();
+            vars.sort();
+
+            for (name, value) in vars {
+                match value {
+                    Some(value) => self.output(format!(
+                        "export {}='{}'\n",
+                        name,
+                        value.replace('\'', "'\\''")
+                    )),
+                    None => self.output(format!("export {}\n", name)),
+                }
+            }
+
+            return 0;
+        }
+
+        let mut status = 0;
+
+        for arg in names.iter() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (arg.as_str(), None),
+            };
+
+            if !is_name(name) {
+                self.error(format!("export: `{}': not a valid identifier\n", arg));
+                status = 1;
+            } else if unexport {
+                self.unexport_var(name);
+            } else {
+                self.export_var(name, value);
+            }
+        }
+
+        status
+    }
+
+    pub fn shopt(&self, name: &str) -> bool {
+        self.options.get(name).copied().unwrap_or_default()
+    }
+
+    fn shopt_builtin(&mut self, argv: &[String]) -> i32 {
+        let mut value = None;
+        let mut names = argv;
+
+        while let Some(option) = names.first().filter(|arg| arg.starts_with('-')) {
+            match option.as_str() {
+                "-s" => value = Some(true),
+                "-u" => value = Some(false),
+                _ => {
+                    self.error(format!("shopt: {}: invalid option\n", option));
+                    return 2;
+                }
+            }
+            names = &names[1..];
+        }
+
+        if let Some(name) = names
+            .iter()
+            .find(|name| !SHOPT_OPTIONS.contains(&name.as_str()))
+        {
+            self.error(format!("shopt: {}: invalid shell option name\n", name));
+            return 1;
+        }
+
+        let names: Vec<&str> = if names.is_empty() {
+            SHOPT_OPTIONS.to_vec()
+        } else {
+            names.iter().map(String::as_str).collect()
+        };
+
+        let mut status = 0;
+
+        for name in names {
+            match value {
+                Some(value) => {
+                    if let Some(option) = self.options.get_mut(name) {
+                        *option = value;
+                    }
+                }
+                None => {
+                    let enabled = self.shopt(name);
+                    if !enabled {
+                        status = 1;
+                    }
+                    self.output(format!(
+                        "{}\t{}\n",
+                        name,
+                        if enabled { "on" } else { "off" }
+                    ));
+                }
+            }
+        }
+
+        status
+    }
+
+    fn set(&mut self, argv: &[String]) -> i32 {
+        let mut args = argv;
+        let mut positional = false;
+
+        while let Some(option) = args
+            .first()
+            .filter(|arg| arg.starts_with('-') || arg.starts_with('+'))
+        {
+            let enable = option.starts_with('-');
+
+            match option.as_str() {
+                "--" => {
+                    args = &args[1..];
+                    positional = true;
+                    break;
+                }
+                "-o" | "+o" => match args.get(1).map(String::as_str) {
+                    Some("vi") => self.editor.vi = enable,
+                    Some("emacs") => self.editor.vi = !enable,
+                    Some(name) => {
+                        self.error(format!("set: {}: invalid option name\n", name));
+                        return 1;
+                    }
+                    None => {
+                        let vi = self.editor.vi;
+                        for (name, enabled) in [("emacs", !vi), ("vi", vi)] {
+                            self.output(format!(
+                                "{}\t{}\n",
+                                name,
+                                if enabled { "on" } else { "off" }
+                            ));
+                        }
+                        return 0;
+                    }
+                },
+                _ => {
+                    self.error(format!("set: {}: invalid option\n", option));
+                    return 2;
+                }
+            }
+
+            args = &args[2..];
+        }
+
+        if positional || !args.is_empty() {
+            self.args.truncate(1);
+            self.args.extend(args.iter().cloned());
+        }
+
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::{parser, test_shell};
+
+    #[test]
+    fn reports_command_types() {
+        let mut shell = test_shell();
+        let list = parser::parse("f() { :; }; type if f cd sh Cargo.toml").unwrap();
+        let output = shell.capture_output(&list).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(
+            lines[..3],
+            [
+                "if is a shell keyword",
+                "f is a function",
+                "cd is a shell builtin"
+            ]
+        );
+        assert!(lines[3].starts_with('/') && lines[3].ends_with("/sh"));
+        assert_eq!(lines[4], "Cargo.toml: not found");
+        assert_eq!(shell.status, 1);
+    }
+}
@@ -0,0 +1,324 @@
+use std::env;
+
+use super::parser;
+use super::Shell;
+use crate::{get_completions, get_path_completions, matches_prefix};
+
+#[derive(Clone, Default)]
+pub struct CompletionSpec {
+    commands: bool,
+    dirs: bool,
+    files: bool,
+    variables: bool,
+    words: Option<String>,
+    function: Option<String>,
+}
+
+impl CompletionSpec {
+    fn format(&self, name: &str) -> String {
+        let mut line = String::from("complete");
+
+        for (enabled, flag) in [
+            (self.commands, " -c"),
+            (self.dirs, " -d"),
+            (self.files, " -f"),
+            (self.variables, " -v"),
+        ] {
+            if enabled {
+                line.push_str(flag);
+            }
+        }
+
+        if let Some(words) = &self.words {
+            line.push_str(&format!(" -W {}", quote(words)));
+        }
+
+        if let Some(function) = &self.function {
+            line.push_str(&format!(" -F {}", function));
+        }
+
+        format!("{} {}\n", line, name)
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+impl Shell<'_> {
+    fn parse_spec<'a>(
+        &mut self,
+        command: &str,
+        argv: &'a [String],
+        actions: &mut Vec<char>,
+    ) -> Option<(CompletionSpec, &'a [String])> {
+        let mut spec = CompletionSpec::default();
+        let mut args = argv;
+
+        while let Some(option) = args
+            .first()
+            .filter(|arg| arg.starts_with('-') && arg.len() > 1)
+        {
+            args = &args[1..];
+
+            if option == "--" {
+                break;
+            }
+
+            for flag in option.chars().skip(1) {
+                match flag {
+                    'c' => spec.commands = true,
+                    'd' => spec.dirs = true,
+                    'f' => spec.files = true,
+                    'v' => spec.variables = true,
+                    'p' | 'r' if command == "complete" => actions.push(flag),
+                    'W' | 'F' => {
+                        let Some(value) = args.first() else {
+                            self.error(format!(
+                                "{}: -{}: option requires an argument\n",
+                                command, flag
+                            ));
+                            return None;
+                        };
+
+                        if flag == 'W' {
+                            spec.words = Some(value.clone());
+                        } else {
+                            spec.function = Some(value.clone());
+                        }
+
+                        args = &args[1..];
+                    }
+                    _ => {
+                        self.error(format!("{}: -{}: invalid option\n", command, flag));
+                        return None;
+                    }
+                }
+            }
+        }
+
+        Some((spec, args))
+    }
+
+    pub fn complete(&mut self, argv: &[String]) -> i32 {
+        let mut actions = vec![];
+
+        let Some((spec, names)) = self.parse_spec("complete", argv, &mut actions) else {
+            return 2;
+        };
+
+        if actions.contains(&'r') {
+            if names.is_empty() {
+                self.completion_specs.clear();
+            }
+
+            for name in names {
+                self.completion_specs.remove(name);
+            }
+
+            return 0;
+        }
+
+        if actions.contains(&'p') || names.is_empty() {
+            let mut names: Vec<String> = if names.is_empty() {
+                self.completion_specs.keys().cloned().collect()
+            } else {
+                names.to_vec()
+            };
+            names.sort();
+
+            let mut output = String::new();
+            let mut status = 0;
+
+            for name in names {
+                match self.completion_specs.get(&name) {
+                    Some(spec) => output.push_str(&spec.format(&name)),
+                    None => {
+                        self.error(format!("complete: {}: no completion specification\n", name));
+                        status = 1;
+                    }
+                }
+            }
+
+            self.output(output);
+            return status;
+        }
+
+        for name in names {
+            self.completion_specs.insert(name.clone(), spec.clone());
+        }
+
+        0
+    }
+
+    pub fn compgen(&mut self, argv: &[String]) -> i32 {
+        let Some((spec, args)) = self.parse_spec("compgen", argv, &mut vec![]) else {
+            return 2;
+        };
+
+        let word = args.first().cloned().unwrap_or_default();
+        let candidates = self.generate_completions(&spec, &word, &[], &word);
+
+        if candidates.is_empty() {
+            return 1;
+        }
+
+        let output: String = candidates
+            .iter()
+            .map(|candidate| format!("{}\n", candidate))
+            .collect();
+
+        self.output(output);
+        0
+    }
+
+    pub fn generate_completions(
+        &mut self,
+        spec: &CompletionSpec,
+        word: &str,
+        words: &[String],
+        line: &str,
+    ) -> Vec<String> {
+        let mut candidates: Vec<String> = vec![];
+        let ignore_case = self.shopt("nocasecomplete");
+
+        if spec.commands {
+            candidates.extend(
+                get_completions(&self.executables, word, ignore_case)
+                    .into_iter()
+                    .map(String::from),
+            );
+        }
+
+        if let Some(words) = &spec.words {
+            candidates.extend(
+                words
+                    .split_whitespace()
+                    .filter(|candidate| matches_prefix(candidate, word, ignore_case))
+                    .map(String::from),
+            );
+        }
+
+        if spec.files {
+            candidates.extend(get_path_completions(word, false, ignore_case));
+        } else if spec.dirs {
+            candidates.extend(
+                get_path_completions(word, false, ignore_case)
+                    .into_iter()
+                    .filter(|candidate| candidate.ends_with('/')),
+            );
+        }
+
+        if spec.variables {
+            let mut names: Vec<String> = self
+                .vars
+                .keys()
+                .cloned()
+                .chain(env::vars().map(|(name, _)| name))
+                .filter(|name| matches_prefix(name, word, ignore_case))
+                .collect();
+            names.sort();
+            candidates.extend(names);
+        }
+
+        if let Some(function) = &spec.function {
+            let command = format!(
+                "{} {} {} {}\n",
+                function,
+                quote(words.first().map_or("", String::as_str)),
+                quote(word),
+                quote(words.last().map_or("", String::as_str))
+            );
+
+            let status = self.status;
+            let saved =
+                self.push_temporary_vars(vec![(String::from("COMP_LINE"), line.to_owned())]);
+
+            if let Some(output) = parser::parse(&command)
+                .ok()
+                .and_then(|list| self.capture_output(&list))
+            {
+                candidates.extend(output.lines().map(String::from));
+            }
+
+            self.pop_temporary_vars(saved);
+            self.status = status;
+            self.substitution_status = None;
+        }
+
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::test_shell;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn compgen(shell: &mut Shell<'_>, argv: &[&str]) -> Vec<String> {
+        let argv = strings(argv);
+        let (spec, args) = shell.parse_spec("compgen", &argv, &mut vec![]).unwrap();
+        let word = args.first().cloned().unwrap_or_default();
+        shell.generate_completions(&spec, &word, &[], &word)
+    }
+
+    #[test]
+    fn generates_words_and_variables() {
+        let mut shell = test_shell();
+        shell.set_var("simple_shell_test_one", String::new());
+        shell.set_var("simple_shell_test_two", String::new());
+
+        assert_eq!(
+            compgen(&mut shell, &["-W", "aa ab b", "a"]),
+            vec!["aa", "ab"]
+        );
+        assert_eq!(
+            compgen(&mut shell, &["-v", "simple_shell_test_"]),
+            vec!["simple_shell_test_one", "simple_shell_test_two"]
+        );
+        assert_eq!(compgen(&mut shell, &["-d", "sr"]), vec!["src/"]);
+        assert!(compgen(&mut shell, &["-W", "aa ab", "b"]).is_empty());
+    }
+
+    #[test]
+    fn calls_completion_functions() {
+        let mut shell = test_shell();
+        shell.execute_list(
+            &parser::parse("f() { echo \"$1 $2 $3\"; echo \"$COMP_LINE\"; }").unwrap(),
+        );
+
+        let (spec, _) = shell
+            .parse_spec("complete", &strings(&["-F", "f"]), &mut vec![])
+            .unwrap();
+
+        assert_eq!(
+            shell.generate_completions(&spec, "ar", &strings(&["git", "ar"]), "git ar"),
+            vec!["git ar ar", "git ar"]
+        );
+        assert_eq!(shell.get_var("COMP_LINE"), None);
+    }
+
+    #[test]
+    fn registers_and_removes_specs() {
+        let mut shell = test_shell();
+
+        assert_eq!(
+            shell.complete(&strings(&["-d", "-W", "it's", "git", "hg"])),
+            0
+        );
+        assert_eq!(
+            shell.completion_specs["git"].format("git"),
+            "complete -d -W 'it'\\''s' git\n"
+        );
+
+        assert_eq!(shell.complete(&strings(&["-r", "git"])), 0);
+        assert!(!shell.completion_specs.contains_key("git"));
+        assert!(shell.completion_specs.contains_key("hg"));
+
+        assert_eq!(shell.complete(&strings(&["-x"])), 2);
+    }
+}
@@ -0,0 +1,1048 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::fuzzy;
+use super::menu::Menu;
+use super::parser;
+use super::sys;
+use super::Shell;
+use crate::{get_completions, get_path_completions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Alt(char),
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Escape,
+    Unknown,
+}
+
+#[derive(Default)]
+pub struct Editor {
+    pub buffer: Vec<char>,
+    pub cursor: usize,
+    pub row: usize,
+    input: VecDeque<u8>,
+    pub completion: Option<Menu>,
+    kill_ring: Vec<String>,
+    killing: bool,
+    yank: Option<(usize, usize)>,
+    pub vi: bool,
+    pub normal: bool,
+    pub replay: VecDeque<Key>,
+    pub replayed: bool,
+    pub recording: Option<Vec<Key>>,
+    pub last_change: Vec<Key>,
+    pub last_find: Option<(char, char)>,
+    pub register: String,
+    pub undo: Vec<(Vec<char>, usize)>,
+    pub history_index: usize,
+    pub draft: Vec<char>,
+    pub search: Option<String>,
+}
+
+static KILL_RING_SIZE: usize = 16;
+
+static COMMAND_KEYWORDS: [&str; 9] = [
+    "if", "then", "elif", "else", "while", "until", "do", "!", "{",
+];
+
+impl Editor {
+    fn text(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.buffer.insert(self.cursor, ch);
+            self.cursor += 1;
+        }
+    }
+
+    pub fn line_start(&self) -> usize {
+        self.buffer[..self.cursor]
+            .iter()
+            .rposition(|ch| *ch == '\n')
+            .map_or(0, |index| index + 1)
+    }
+
+    pub fn line_end(&self) -> usize {
+        self.buffer[self.cursor..]
+            .iter()
+            .position(|ch| *ch == '\n')
+            .map_or(self.buffer.len(), |index| self.cursor + index)
+    }
+
+    fn word_start(&self, is_word: fn(char) -> bool) -> usize {
+        let mut index = self.cursor;
+
+        while index > 0 && !is_word(self.buffer[index - 1]) {
+            index -= 1;
+        }
+        while index > 0 && is_word(self.buffer[index - 1]) {
+            index -= 1;
+        }
+
+        index
+    }
+
+    fn word_end(&self, is_word: fn(char) -> bool) -> usize {
+        let mut index = self.cursor;
+
+        while index < self.buffer.len() && !is_word(self.buffer[index]) {
+            index += 1;
+        }
+        while index < self.buffer.len() && is_word(self.buffer[index]) {
+            index += 1;
+        }
+
+        index
+    }
+
+    fn kill(&mut self, start: usize, end: usize, append: bool) {
+        let backward = start < self.cursor;
+        let text: String = self.buffer.drain(start..end).collect();
+
+        self.cursor = start;
+        self.killing = true;
+
+        if text.is_empty() {
+            return;
+        }
+
+        match self.kill_ring.last_mut() {
+            Some(last) if append && backward => last.insert_str(0, &text),
+            Some(last) if append => last.push_str(&text),
+            _ => {
+                self.kill_ring.push(text);
+                if self.kill_ring.len() > KILL_RING_SIZE {
+                    self.kill_ring.remove(0);
+                }
+            }
+        }
+    }
+
+    fn yank(&mut self, previous: Option<(usize, usize)>) {
+        if self.kill_ring.is_empty() {
+            return;
+        }
+
+        let index = match previous {
+            Some((start, index)) => {
+                self.buffer.drain(start..self.cursor);
+                self.cursor = start;
+                (index + self.kill_ring.len() - 1) % self.kill_ring.len()
+            }
+            None => self.kill_ring.len() - 1,
+        };
+
+        let start = self.cursor;
+        let text = self.kill_ring[index].clone();
+
+        self.insert(&text);
+        self.yank = Some((start, index));
+    }
+
+    fn current_word(&self) -> (usize, String, Vec<String>) {
+        let mut start = 0;
+        let mut word = String::new();
+        let mut words = vec![];
+        let mut quote = None;
+        let mut escaped = false;
+
+        for index in 0..self.cursor {
+            let ch = self.buffer[index];
+
+            match (quote, ch) {
+                _ if escaped => {
+                    word.push(ch);
+                    escaped = false;
+                }
+                (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+                (None, '\'' | '"') => quote = Some(ch),
+                (Some('\''), _) => word.push(ch),
+                (_, '\\') => escaped = true,
+                (None, ch) if ch.is_whitespace() || "|&;<>()".contains(ch) => {
+                    let word = std::mem::take(&mut word);
+
+                    let keyword = words.is_empty() && COMMAND_KEYWORDS.contains(&word.as_str());
+
+                    if !word.is_empty() && !keyword {
+                        words.push(word);
+                    }
+
+                    if "|&;()\n".contains(ch) {
+                        words.clear();
+                    }
+
+                    start = index + 1;
+                }
+                _ => word.push(ch),
+            }
+        }
+
+        (start, word, words)
+    }
+
+    fn transpose(&mut self) {
+        let (start, end) = (self.line_start(), self.line_end());
+
+        if self.cursor == end && self.cursor >= start + 2 {
+            self.buffer.swap(self.cursor - 1, self.cursor - 2);
+        } else if self.cursor != end && self.cursor > start {
+            self.buffer.swap(self.cursor - 1, self.cursor);
+            self.cursor += 1;
+        }
+    }
+}
+
+pub fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn common_prefix(first: &str, rest: &[String], ignore_case: bool) -> String {
+    let mut length = first.chars().count();
+
+    for completion in rest {
+        length = first
+            .chars()
+            .zip(completion.chars())
+            .take(length)
+            .take_while(|(a, b)| a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase())))
+            .count();
+    }
+
+    first.chars().take(length).collect()
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for (index, ch) in text.chars().enumerate() {
+        if (index > 0 || ch != '~')
+            && (ch.is_whitespace() || "'\"\\$`&;|()<>*?[]{}!#~".contains(ch))
+        {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+
+    escaped
+}
+
+fn is_blank_word(ch: char) -> bool {
+    !ch.is_whitespace()
+}
+
+pub fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+impl Shell<'_> {
+    pub fn read_input(&mut self) -> Option<String> {
+        self.editor.buffer.clear();
+        self.editor.cursor = 0;
+        self.editor.row = 0;
+        self.editor.completion = None;
+        self.editor.normal = false;
+        self.editor.recording = None;
+        self.editor.undo = vec![(vec![], 0)];
+        self.editor.history_index = self.history.len();
+        self.editor.draft.clear();
+        self.editor.search = None;
+        self.refresh();
+
+        loop {
+            let key = match self.read_key()? {
+                Key::Ctrl('r') if self.shopt("fuzzycomplete") => {
+                    self.pick_history();
+                    self.refresh();
+                    continue;
+                }
+                Key::Ctrl('t') if self.shopt("fuzzycomplete") => {
+                    self.pick_file();
+                    self.refresh();
+                    continue;
+                }
+                Key::Ctrl(ch @ ('r' | 's')) => {
+                    let key = self.search_history(ch == 'r')?;
+                    self.refresh();
+                    key
+                }
+                key => key,
+            };
+
+            if let Some(menu) = self.editor.completion.take() {
+                if self.menu_key(menu, key)? {
+                    self.refresh();
+                    continue;
+                }
+            }
+
+            if self.editor.vi {
+                match key {
+                    Key::Enter | Key::Ctrl('c') | Key::Ctrl('d') => {}
+                    Key::Escape if !self.editor.normal => {
+                        self.vi_escape();
+                        self.refresh();
+                        continue;
+                    }
+                    Key::Alt(ch) if !self.editor.normal => {
+                        if let Some(recording) = self.editor.recording.as_mut() {
+                            if recording.last() == Some(&key) {
+                                recording.pop();
+                                recording.push(Key::Escape);
+                            }
+                        }
+                        self.editor.replay.push_front(Key::Char(ch));
+                        self.vi_escape();
+                        self.refresh();
+                        continue;
+                    }
+                    key if self.editor.normal => {
+                        self.vi_normal(key)?;
+                        self.refresh();
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            let killing = std::mem::take(&mut self.editor.killing);
+            let yank = self.editor.yank.take();
+
+            match key {
+                Key::Char(ch) => self.editor.insert(ch.encode_utf8(&mut [0; 4])),
+                Key::Tab => self.complete_word(),
+                Key::Enter => {
+                    let mut input = self.editor.text();
+                    input.push('\n');
+
+                    if parser::is_complete(&input) {
+                        self.move_to_end();
+                        self.output("\r\n");
+                        return Some(input);
+                    }
+
+                    self.editor.cursor = self.editor.buffer.len();
+                    self.editor.insert("\n");
+                }
+                Key::Ctrl('c') => {
+                    self.move_to_end();
+                    self.output("^C\r\n");
+                    self.editor.buffer.clear();
+                    self.editor.cursor = 0;
+                    self.editor.row = 0;
+                    self.editor.normal = false;
+                    self.status = 130;
+                }
+                Key::Backspace | Key::Ctrl('h') => {
+                    if self.editor.cursor > 0 {
+                        self.editor.cursor -= 1;
+                        self.editor.buffer.remove(self.editor.cursor);
+                    }
+                }
+                Key::Ctrl('d') if self.editor.buffer.is_empty() => {
+                    self.output("exit\r\n");
+                    return None;
+                }
+                Key::Delete | Key::Ctrl('d') => {
+                    if self.editor.cursor < self.editor.buffer.len() {
+                        self.editor.buffer.remove(self.editor.cursor);
+                    }
+                }
+                Key::Left | Key::Ctrl('b') => {
+                    self.editor.cursor = self.editor.cursor.saturating_sub(1);
+                }
+                Key::Right | Key::Ctrl('f') => {
+                    self.editor.cursor = (self.editor.cursor + 1).min(self.editor.buffer.len());
+                }
+                Key::Up | Key::Ctrl('p') => self.history_previous(),
+                Key::Down | Key::Ctrl('n') => self.history_next(),
+                Key::Home | Key::Ctrl('a') => self.editor.cursor = self.editor.line_start(),
+                Key::End | Key::Ctrl('e') => self.editor.cursor = self.editor.line_end(),
+                Key::Alt('b') => self.editor.cursor = self.editor.word_start(is_word),
+                Key::Alt('f') => self.editor.cursor = self.editor.word_end(is_word),
+                Key::Ctrl('w') => {
+                    let start = self.editor.word_start(is_blank_word);
+                    self.editor.kill(start, self.editor.cursor, killing);
+                }
+                Key::Alt('\x7f') => {
+                    let start = self.editor.word_start(is_word);
+                    self.editor.kill(start, self.editor.cursor, killing);
+                }
+                Key::Alt('d') => {
+                    let end = self.editor.word_end(is_word);
+                    self.editor.kill(self.editor.cursor, end, killing);
+                }
+                Key::Ctrl('u') => {
+                    let start = self.editor.line_start();
+                    self.editor.kill(start, self.editor.cursor, killing);
+                }
+                Key::Ctrl('k') => {
+                    let end = self.editor.line_end();
+                    self.editor.kill(self.editor.cursor, end, killing);
+                }
+                Key::Ctrl('y') => self.editor.yank(None),
+                Key::Alt('y') if yank.is_some() => self.editor.yank(yank),
+                Key::Ctrl('t') => self.editor.transpose(),
+                Key::Ctrl('l') => {
+                    self.output("\x1B[H\x1B[2J");
+                    self.editor.row = 0;
+                }
+                Key::Ctrl('v') => match self.read_quoted()? {
+                    Key::Char(ch) => self.editor.insert(ch.encode_utf8(&mut [0; 4])),
+                    _ => continue,
+                },
+                _ => continue,
+            }
+
+            self.refresh();
+        }
+    }
+
+    fn read_byte(&mut self, wait: bool) -> Option<u8> {
+        if self.editor.input.is_empty() {
+            if !wait && !sys::poll_input(libc::STDIN_FILENO, 25) {
+                return None;
+            }
+
+            let mut buffer = [0u8; 1024];
+
+            match sys::read(libc::STDIN_FILENO, &mut buffer) {
+                Ok(0) => return None,
+                Ok(count) => self.editor.input.extend(&buffer[..count]),
+                Err(error) => {
+                    self.error(format!("sh: failed to read input, error: {}\n", error));
+                    return None;
+                }
+            }
+        }
+
+        self.editor.input.pop_front()
+    }
+
+    pub fn read_key(&mut self) -> Option<Key> {
+        self.editor.replayed = !self.editor.replay.is_empty();
+
+        if let Some(key) = self.editor.replay.pop_front() {
+            return Some(key);
+        }
+
+        let key = self.read_raw_key()?;
+
+        if let Some(recording) = self.editor.recording.as_mut() {
+            recording.push(key);
+        }
+
+        Some(key)
+    }
+
+    fn read_quoted(&mut self) -> Option<Key> {
+        if let Some(key) = self.editor.replay.pop_front() {
+            return Some(key);
+        }
+
+        let byte = self.read_byte(true)?;
+
+        let key = if byte.is_ascii() {
+            Key::Char(byte as char)
+        } else {
+            self.editor.input.push_front(byte);
+            self.read_raw_key()?
+        };
+
+        if let Some(recording) = self.editor.recording.as_mut() {
+            recording.push(key);
+        }
+
+        Some(key)
+    }
+
+    fn read_raw_key(&mut self) -> Option<Key> {
+        let byte = self.read_byte(true)?;
+
+        let key = match byte {
+            b'\r' | b'\n' => Key::Enter,
+            b'\t' => Key::Tab,
+            0x7f => Key::Backspace,
+            0x1b => self.read_escape(),
+            0x00..=0x1f => Key::Ctrl((byte + 0x60) as char),
+            _ => {
+                let length = match byte {
+                    0xf0..=0xf7 => 4,
+                    0xe0..=0xef => 3,
+                    0xc0..=0xdf => 2,
+                    _ => 1,
+                };
+
+                let mut bytes = vec![byte];
+
+                for _ in 1..length {
+                    bytes.push(self.read_byte(true)?);
+                }
+
+                match std::str::from_utf8(&bytes) {
+                    Ok(text) => Key::Char(text.chars().next().unwrap_or_default()),
+                    Err(_) => Key::Unknown,
+                }
+            }
+        };
+
+        Some(key)
+    }
+
+    fn read_escape(&mut self) -> Key {
+        let byte = match self.read_byte(false) {
+            Some(byte) => byte,
+            None => return Key::Escape,
+        };
+
+        if byte != b'[' && byte != b'O' {
+            return match byte {
+                0x7f => Key::Alt('\x7f'),
+                byte if byte.is_ascii() && !byte.is_ascii_control() => Key::Alt(byte as char),
+                _ => Key::Unknown,
+            };
+        }
+
+        let mut params = String::new();
+
+        loop {
+            let byte = match self.read_byte(false) {
+                Some(byte) => byte,
+                None => return Key::Unknown,
+            };
+
+            if byte.is_ascii_digit() || byte == b';' {
+                params.push(byte as char);
+                continue;
+            }
+
+            return match (byte, params.split(';').next().unwrap_or_default()) {
+                (b'A', _) => Key::Up,
+                (b'B', _) => Key::Down,
+                (b'C', _) => Key::Right,
+                (b'D', _) => Key::Left,
+                (b'H', _) => Key::Home,
+                (b'F', _) => Key::End,
+                (b'Z', _) => Key::BackTab,
+                (b'~', "1" | "7") => Key::Home,
+                (b'~', "4" | "8") => Key::End,
+                (b'~', "3") => Key::Delete,
+                _ => Key::Unknown,
+            };
+        }
+    }
+
+    fn prompt_text(&self, continuation: bool) -> String {
+        if continuation {
+            return String::from("> ");
+        }
+
+        if let Some(search) = &self.editor.search {
+            return search.clone();
+        }
+
+        match (self.editor.vi, self.editor.normal) {
+            (true, false) => String::from("(ins)$ "),
+            (true, true) => String::from("(cmd)$ "),
+            _ => String::from("$ "),
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let (width, height) = sys::terminal_size();
+        let mut output = String::new();
+
+        if self.editor.row > 0 {
+            output.push_str(&format!("\x1B[{}A", self.editor.row));
+        }
+
+        output.push_str("\r\x1B[J");
+
+        let (mut row, mut col) = (0, 0);
+        let mut cursor = (0, 0);
+
+        let put = |output: &mut String, ch: char, row: &mut usize, col: &mut usize| {
+            let char_width = char_width(ch);
+
+            if *col + char_width > width {
+                output.push_str("\r\n");
+                *row += 1;
+                *col = 0;
+            }
+
+            output.push(ch);
+            *col += char_width;
+
+            if *col >= width {
+                output.push_str("\r\n");
+                *row += 1;
+                *col = 0;
+            }
+        };
+
+        for ch in self.prompt_text(false).chars() {
+            put(&mut output, ch, &mut row, &mut col);
+        }
+
+        for (index, ch) in self.editor.buffer.iter().enumerate() {
+            if index == self.editor.cursor {
+                cursor = (row, col);
+            }
+
+            if *ch == '\n' {
+                output.push_str("\r\n");
+                row += 1;
+                col = 0;
+
+                for ch in self.prompt_text(true).chars() {
+                    put(&mut output, ch, &mut row, &mut col);
+                }
+            } else if ch.is_ascii_control() {
+                put(&mut output, '^', &mut row, &mut col);
+                put(&mut output, (*ch as u8 ^ 0x40) as char, &mut row, &mut col);
+            } else {
+                put(&mut output, *ch, &mut row, &mut col);
+            }
+        }
+
+        if self.editor.cursor == self.editor.buffer.len() {
+            cursor = (row, col);
+        }
+
+        if let Some(menu) = self
+            .editor
+            .completion
+            .as_mut()
+            .filter(|menu| menu.visible())
+        {
+            for line in menu.render(width, height.saturating_sub(row + 2)) {
+                output.push_str("\r\n");
+                output.push_str(&line);
+                row += 1;
+            }
+        }
+
+        if row > cursor.0 {
+            output.push_str(&format!("\x1B[{}A", row - cursor.0));
+        }
+
+        output.push('\r');
+
+        if cursor.1 > 0 {
+            output.push_str(&format!("\x1B[{}C", cursor.1));
+        }
+
+        self.editor.row = cursor.0;
+        self.output(output);
+    }
+
+    fn move_to_end(&mut self) {
+        self.editor.cursor = self.editor.buffer.len();
+        self.refresh();
+    }
+
+    fn complete_word(&mut self) {
+        let (start, word, words) = self.editor.current_word();
+        let ignore_case = self.shopt("nocasecomplete");
+
+        if let Some(spec) = words
+            .first()
+            .and_then(|command| self.completion_specs.get(command))
+            .cloned()
+        {
+            let line: String = self.editor.buffer[..self.editor.cursor].iter().collect();
+            let completions = self.generate_completions(&spec, &word, &words, &line);
+            self.insert_completion(start, &word, completions, false);
+            return;
+        }
+
+        let path = !words.is_empty() || word.contains('/') || word.starts_with('~');
+
+        if !path && word.is_empty() {
+            return;
+        }
+
+        let completions: Vec<String> = if path {
+            get_path_completions(&word, words.is_empty(), ignore_case)
+        } else {
+            get_completions(&self.executables, &word, ignore_case)
+                .into_iter()
+                .map(String::from)
+                .collect()
+        };
+
+        if !completions.is_empty() || word.is_empty() || !self.shopt("fuzzycomplete") {
+            self.insert_completion(start, &word, completions, false);
+            return;
+        }
+
+        let candidates = if path {
+            let dir = &word[..word.rfind('/').map_or(0, |index| index + 1)];
+            get_path_completions(dir, words.is_empty(), false)
+        } else {
+            get_completions(&self.executables, "", false)
+                .into_iter()
+                .map(String::from)
+                .collect()
+        };
+
+        let completions = fuzzy::complete(&word, candidates);
+        self.insert_completion(start, &word, completions, true);
+    }
+
+    fn insert_completion(
+        &mut self,
+        start: usize,
+        word: &str,
+        mut completions: Vec<String>,
+        fuzzy: bool,
+    ) {
+        let mut seen = HashSet::new();
+        completions.retain(|completion| seen.insert(completion.clone()));
+
+        let replacement = match completions.as_slice() {
+            [] => {
+                self.output("\x07");
+                return;
+            }
+            [completion] if completion.ends_with('/') => escape(completion),
+            [completion] => format!("{} ", escape(completion)),
+            [first, rest @ ..] => {
+                let prefix = common_prefix(first, rest, self.shopt("nocasecomplete"));
+
+                if prefix.chars().count() <= word.chars().count() {
+                    self.editor.completion = Some(Menu::new(start, word, completions, fuzzy));
+                    self.output("\x07");
+                    return;
+                }
+
+                escape(&prefix)
+            }
+        };
+
+        self.editor.buffer.drain(start..self.editor.cursor);
+        self.editor.cursor = start;
+        self.editor.insert(&replacement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::test_shell;
+
+    #[test]
+    fn finds_line_bounds() {
+        let mut editor = Editor::default();
+        editor.insert("if true\nthen echo");
+        assert_eq!(editor.cursor, 17);
+        assert_eq!(editor.line_start(), 8);
+        assert_eq!(editor.line_end(), 17);
+
+        editor.cursor = 3;
+        editor.insert("x");
+        assert_eq!(editor.text(), "if xtrue\nthen echo");
+        assert_eq!(editor.line_start(), 0);
+        assert_eq!(editor.line_end(), 8);
+    }
+
+    #[test]
+    fn decodes_keys() {
+        let mut shell = test_shell();
+        shell
+            .editor
+            .input
+            .extend("a\x01\x1b[3~\x1b[1;5C\x1bOH界\x1bb\x7f\t\r".bytes());
+
+        let keys: Vec<Key> = (0..10).map(|_| shell.read_key().unwrap()).collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char('a'),
+                Key::Ctrl('a'),
+                Key::Delete,
+                Key::Right,
+                Key::Home,
+                Key::Char('界'),
+                Key::Alt('b'),
+                Key::Backspace,
+                Key::Tab,
+                Key::Enter,
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_quoted_keys_literally() {
+        let mut shell = test_shell();
+        shell.editor.input.extend("\x01\x1b[A\r界".bytes());
+        shell.editor.recording = Some(vec![]);
+
+        let keys: Vec<Key> = (0..6).map(|_| shell.read_quoted().unwrap()).collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char('\x01'),
+                Key::Char('\x1b'),
+                Key::Char('['),
+                Key::Char('A'),
+                Key::Char('\r'),
+                Key::Char('界'),
+            ]
+        );
+        assert_eq!(shell.editor.recording, Some(keys));
+    }
+
+    #[test]
+    fn measures_character_width() {
+        assert_eq!(char_width('a'), 1);
+        assert_eq!(char_width('界'), 2);
+    }
+
+    fn editor_with(text: &str) -> Editor {
+        let mut editor = Editor::default();
+        editor.insert(text);
+        editor
+    }
+
+    #[test]
+    fn moves_by_words() {
+        let mut editor = editor_with("echo foo-bar baz");
+        assert_eq!(editor.word_start(is_word), 13);
+        assert_eq!(editor.word_start(is_blank_word), 13);
+
+        editor.cursor = 12;
+        assert_eq!(editor.word_start(is_word), 9);
+        assert_eq!(editor.word_start(is_blank_word), 5);
+
+        editor.cursor = 0;
+        assert_eq!(editor.word_end(is_word), 4);
+    }
+
+    #[test]
+    fn kills_and_yanks() {
+        let mut editor = editor_with("echo one two");
+
+        let start = editor.word_start(is_blank_word);
+        editor.kill(start, editor.cursor, false);
+        let start = editor.word_start(is_blank_word);
+        editor.kill(start, editor.cursor, true);
+        assert_eq!(editor.text(), "echo ");
+        assert_eq!(editor.kill_ring, vec!["one two"]);
+
+        editor.kill(0, 4, false);
+        assert_eq!(editor.kill_ring, vec!["one two", "echo"]);
+
+        editor.cursor = 1;
+        editor.yank(None);
+        assert_eq!(editor.text(), " echo");
+
+        let previous = editor.yank.take();
+        editor.yank(previous);
+        assert_eq!(editor.text(), " one two");
+        assert_eq!(editor.cursor, 8);
+    }
+
+    #[test]
+    fn transposes_characters() {
+        let mut editor = editor_with("ab");
+        editor.transpose();
+        assert_eq!(editor.text(), "ba");
+
+        let mut editor = editor_with("abc");
+        editor.cursor = 1;
+        editor.transpose();
+        assert_eq!(editor.text(), "bac");
+        assert_eq!(editor.cursor, 2);
+    }
+
+    fn type_keys(shell: &mut Shell<'_>, keys: &str) {
+        shell.editor.input.extend(keys.bytes());
+
+        loop {
+            let key = if shell.editor.replay.is_empty() && shell.editor.input.front() == Some(&0x1b)
+            {
+                shell.editor.input.pop_front();
+                if let Some(recording) = shell.editor.recording.as_mut() {
+                    recording.push(Key::Escape);
+                }
+                Key::Escape
+            } else if shell.editor.replay.is_empty() && shell.editor.input.is_empty() {
+                break;
+            } else {
+                shell.read_key().unwrap()
+            };
+
+            match key {
+                Key::Escape if !shell.editor.normal => shell.vi_escape(),
+                key if shell.editor.normal => shell.vi_normal(key).unwrap(),
+                Key::Char(ch) => shell.editor.insert(ch.encode_utf8(&mut [0; 4])),
+                _ => {}
+            }
+        }
+    }
+
+    fn edit(keys: &str) -> (String, usize) {
+        let mut shell = test_shell();
+        shell.editor.vi = true;
+        type_keys(&mut shell, keys);
+        (shell.editor.buffer.iter().collect(), shell.editor.cursor)
+    }
+
+    #[test]
+    fn moves_in_normal_mode() {
+        let line = "echo one two three\x1b";
+
+        assert_eq!(edit(&format!("{}0w", line)).1, 5);
+        assert_eq!(edit(&format!("{}02w", line)).1, 9);
+        assert_eq!(edit(&format!("{}0e", line)).1, 3);
+        assert_eq!(edit(&format!("{}b", line)).1, 13);
+        assert_eq!(edit(&format!("{}0ft", line)).1, 9);
+        assert_eq!(edit(&format!("{}0fe;", line)).1, 16);
+        assert_eq!(edit(&format!("{}0$", line)).1, 17);
+    }
+
+    #[test]
+    fn applies_operators() {
+        let line = "echo one two three\x1b";
+
+        assert_eq!(edit(&format!("{}0wdw", line)).0, "echo two three");
+        assert_eq!(edit(&format!("{}0w2dw", line)).0, "echo three");
+        assert_eq!(edit(&format!("{}0wcwZ\x1b", line)).0, "echo Z two three");
+        assert_eq!(edit(&format!("{}0dd", line)).0, "");
+        assert_eq!(edit(&format!("{}0wD", line)).0, "echo ");
+        assert_eq!(edit(&format!("{}03x", line)).0, "o one two three");
+        assert_eq!(edit(&format!("{}0wyw$p", line)).0, "echo one two threeone ");
+        assert_eq!(edit(&format!("{}0~~", line)).0, "ECho one two three");
+        assert_eq!(edit(&format!("{}0rX", line)).0, "Xcho one two three");
+    }
+
+    #[test]
+    fn changes_words_with_counts() {
+        let line = "echo one two three four\x1b";
+
+        assert_eq!(edit(&format!("{}0w2cwZ\x1b", line)).0, "echo Z three four");
+        assert_eq!(edit(&format!("{}0w3cWZ\x1b", line)).0, "echo Z four");
+        assert_eq!(edit(&format!("{}0w9cwZ\x1b", line)).0, "echo Z");
+    }
+
+    #[test]
+    fn reads_counts_after_operators() {
+        let line = "echo one two three four\x1b";
+
+        assert_eq!(edit(&format!("{}0wd2w", line)).0, "echo three four");
+        assert_eq!(edit(&format!("{}0wc2wZ\x1b", line)).0, "echo Z three four");
+        assert_eq!(edit(&format!("{}02d2w", line)).0, "four");
+        assert_eq!(
+            edit(&format!("{}0y3w$p", line)).0,
+            "echo one two three fourecho one two "
+        );
+        assert_eq!(edit(&format!("{}0d99999999999999999999w", line)).0, "");
+    }
+
+    #[test]
+    fn repeats_and_undoes_changes() {
+        let line = "echo one two three\x1b";
+
+        assert_eq!(edit(&format!("{}0dw.", line)).0, "two three");
+        assert_eq!(edit(&format!("{}0wcwZ\x1bw.", line)).0, "echo Z Z three");
+        assert_eq!(
+            edit(&format!("{}0dwu", line)),
+            (String::from("echo one two three"), 0)
+        );
+    }
+
+    #[test]
+    fn caps_repeat_counts() {
+        assert_eq!(edit("echo one\x1b099999999999999999999999x").0, "");
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape("a b"), "a\\ b");
+        assert_eq!(escape("~/it's"), "~/it\\'s");
+        assert_eq!(escape("a~b"), "a\\~b");
+    }
+
+    #[test]
+    fn finds_current_word() {
+        let words =
+            |items: &[&str]| -> Vec<String> { items.iter().map(|item| item.to_string()).collect() };
+
+        assert_eq!(
+            editor_with("ls 'my fi").current_word(),
+            (3, String::from("my fi"), words(&["ls"]))
+        );
+        assert_eq!(
+            editor_with("cat a\\ b").current_word(),
+            (4, String::from("a b"), words(&["cat"]))
+        );
+        assert_eq!(
+            editor_with("echo a | gr").current_word(),
+            (9, String::from("gr"), words(&[]))
+        );
+        assert_eq!(
+            editor_with("if tr").current_word(),
+            (3, String::from("tr"), words(&[]))
+        );
+        assert_eq!(
+            editor_with("echo a; git co").current_word(),
+            (12, String::from("co"), words(&["git"]))
+        );
+    }
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_common_prefix() {
+        assert_eq!(
+            common_prefix("foobar", &strings(&["foobaz", "fooqux"]), false),
+            "foo"
+        );
+        assert_eq!(common_prefix("abc", &[], false), "abc");
+        assert_eq!(common_prefix("abc", &strings(&["xyz"]), false), "");
+    }
+
+    #[test]
+    fn finds_common_prefix_ignoring_case() {
+        assert_eq!(common_prefix("Makefile", &strings(&["makeit"]), false), "");
+        assert_eq!(
+            common_prefix("Makefile", &strings(&["makeit"]), true),
+            "Make"
+        );
+        assert_eq!(common_prefix("éa", &strings(&["Éb"]), true), "é");
+    }
+}
@@ -0,0 +1,787 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+
+use libc::pid_t;
+
+use super::ast::{
+    AndOr, Command, CompoundCommand, Connector, List, Pipeline, Redirect, RedirectKind,
+    SimpleCommand,
+};
+use super::jobs::ProcessGroup;
+use super::sys::{self, FdGuard};
+use super::Shell;
+use crate::{find_command, is_executable, BUILTINS};
+
+pub enum Flow {
+    Break(usize),
+    Continue(usize),
+    Return,
+    Interrupt,
+}
+
+impl Shell<'_> {
+    pub fn execute_list(&mut self, list: &List) -> i32 {
+        let mut status = 0;
+
+        for item in list.items.iter() {
+            status = if item.background {
+                self.execute_background(item)
+            } else {
+                self.execute_and_or(item)
+            };
+
+            if self.flow.is_some() {
+                break;
+            }
+        }
+
+        status
+    }
+
+    fn execute_and_or(&mut self, and_or: &AndOr) -> i32 {
+        let mut status = self.execute_pipeline(&and_or.first);
+        self.status = status;
+
+        for (connector, pipeline) in and_or.rest.iter() {
+            if sys::take_interrupt() {
+                self.flow = Some(Flow::Interrupt);
+            }
+
+            if self.flow.is_some() {
+                break;
+            }
+
+            match connector {
+                Connector::And if status != 0 => continue,
+                Connector::Or if status == 0 => continue,
+                _ => {}
+            }
+
+            status = self.execute_pipeline(pipeline);
+            self.status = status;
+        }
+
+        if sys::take_interrupt() {
+            self.flow = Some(Flow::Interrupt);
+        }
+
+        status
+    }
+
+    fn execute_background(&mut self, and_or: &AndOr) -> i32 {
+        let job_control = self.job_control;
+
+        self.group = self.new_group(false);
+
+        let result = self.fork_child(|shell| {
+            if !job_control {
+                if let Ok(null) = File::open("/dev/null") {
+                    unsafe { libc::dup2(null.as_raw_fd(), 0) };
+                }
+            }
+            match (&and_or.first, and_or.rest.is_empty()) {
+                (pipeline, true) if !pipeline.bang && pipeline.commands.len() == 1 => {
+                    shell.execute_command(&pipeline.commands[0], true)
+                }
+                _ => shell.execute_and_or(and_or),
+            }
+        });
+
+        let group = self.group.take();
+
+        let status = match result {
+            Ok(pid) => {
+                self.add_job(pid, group, and_or.to_string());
+                self.last_background = Some(pid);
+                0
+            }
+            Err(status) => status,
+        };
+
+        self.status = status;
+        status
+    }
+
+    fn new_group(&self, foreground: bool) -> Option<ProcessGroup> {
+        self.job_control.then_some(ProcessGroup {
+            pgid: 0,
+            foreground,
+        })
+    }
+
+    fn execute_pipeline(&mut self, pipeline: &Pipeline) -> i32 {
+        let status = if pipeline.commands.len() == 1 {
+            self.execute_command(&pipeline.commands[0], false)
+        } else {
+            self.execute_stages(&pipeline.commands)
+        };
+
+        if pipeline.bang {
+            (status == 0) as i32
+        } else {
+            status
+        }
+    }
+
+    fn execute_stages(&mut self, commands: &[Command]) -> i32 {
+        let mut pids = vec![];
+        let mut input: Option<OwnedFd> = None;
+        let mut status = None;
+
+        self.group = self.new_group(true);
+
+        for (index, command) in commands.iter().enumerate() {
+            let last = index + 1 == commands.len();
+            let mut guard = FdGuard::new();
+
+            let reader = match Shell::connect_pipe(&mut guard, input.take(), last) {
+                Ok(reader) => reader,
+                Err(error) => {
+                    self.error(format!("sh: failed to create pipe, error: {}\n", error));
+                    break;
+                }
+            };
+
+            let reader_fd = reader.as_ref().map(|fd| fd.as_raw_fd());
+
+            let result = self.fork_child(|shell| {
+                if let Some(fd) = reader_fd {
+                    unsafe { libc::close(fd) };
+                }
+                shell.execute_command(command, true)
+            });
+
+            drop(guard);
+            input = reader;
+
+            match result {
+                Ok(pid) => pids.push(pid),
+                Err(code) => status = Some(code),
+            }
+        }
+
+        let group = self.group.take();
+        let text: Vec<String> = commands.iter().map(Command::to_string).collect();
+        let code = self.wait_foreground(pids, group, text.join(" | "));
+
+        status.unwrap_or(code)
+    }
+
+    pub fn capture_output(&mut self, list: &List) -> Option<String> {
+        let (reader, writer) = match sys::pipe() {
+            Ok(pipe) => pipe,
+            Err(error) => {
+                self.error(format!("sh: failed to create pipe, error: {}\n", error));
+                return None;
+            }
+        };
+
+        let mut guard = FdGuard::new();
+
+        if let Err(error) = guard.redirect(1, writer) {
+            self.error(format!("sh: {}\n", error));
+            return None;
+        }
+
+        let reader_fd = reader.as_raw_fd();
+
+        let result = self.fork_child(|shell| {
+            unsafe { libc::close(reader_fd) };
+            shell.execute_list(list)
+        });
+
+        drop(guard);
+
+        let mut output = vec![];
+        File::from(reader)
+            .read_to_end(&mut output)
+            .unwrap_or_default();
+
+        let status = match result {
+            Ok(pid) => sys::wait(pid),
+            Err(status) => status,
+        };
+
+        self.status = status;
+        self.substitution_status = Some(status);
+
+        let mut output = String::from_utf8_lossy(&output).into_owned();
+        output.truncate(output.trim_end_matches('\n').len());
+
+        Some(output)
+    }
+
+    fn connect_pipe(
+        guard: &mut FdGuard,
+        input: Option<OwnedFd>,
+        last: bool,
+    ) -> io::Result<Option<OwnedFd>> {
+        if let Some(fd) = input {
+            guard.redirect(0, fd)?;
+        }
+
+        if last {
+            return Ok(None);
+        }
+
+        let (reader, writer) = sys::pipe()?;
+        guard.redirect(1, writer)?;
+        Ok(Some(reader))
+    }
+
+    fn fork_child<F: FnOnce(&mut Self) -> i32>(&mut self, f: F) -> Result<pid_t, i32> {
+        match sys::fork() {
+            Ok(0) => {
+                if let Some(group) = self.group.take() {
+                    let pgid = match group.pgid {
+                        0 => unsafe { libc::getpid() },
+                        pgid => pgid,
+                    };
+
+                    unsafe { libc::setpgid(0, pgid) };
+
+                    if group.foreground {
+                        unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, pgid) };
+                    }
+                }
+
+                self.job_control = false;
+                sys::reset_signals();
+
+                let status = f(self);
+                self.stdout.flush().unwrap_or_default();
+                self.stderr.flush().unwrap_or_default();
+                unsafe { libc::_exit(status) }
+            }
+            Ok(pid) => {
+                if let Some(group) = self.group.as_mut() {
+                    if group.pgid == 0 {
+                        group.pgid = pid;
+                    }
+                    unsafe { libc::setpgid(pid, group.pgid) };
+                }
+                Ok(pid)
+            }
+            Err(error) => {
+                self.error(format!("sh: failed to fork, error: {}\n", error));
+                Err(1)
+            }
+        }
+    }
+
+    fn execute_command(&mut self, command: &Command, forked: bool) -> i32 {
+        match command {
+            Command::Simple(simple) => self.execute_simple(simple, forked),
+            Command::Compound(compound, redirects) => {
+                let mut guard = FdGuard::new();
+
+                if !self.apply_redirects(redirects, &mut guard) {
+                    return 1;
+                }
+
+                self.execute_compound(compound, forked)
+            }
+            Command::Function(name, body) => {
+                self.functions.insert(name.clone(), Rc::clone(body));
+                0
+            }
+        }
+    }
+
+    fn execute_compound(&mut self, compound: &CompoundCommand, forked: bool) -> i32 {
+        match compound {
+            CompoundCommand::Group(list) => self.execute_list(list),
+            CompoundCommand::Subshell(list) => {
+                if forked {
+                    return self.execute_list(list);
+                }
+
+                self.group = self.new_group(true);
+                let result = self.fork_child(|shell| shell.execute_list(list));
+                let group = self.group.take();
+
+                match result {
+                    Ok(pid) => self.wait_foreground(vec![pid], group, compound.to_string()),
+                    Err(status) => status,
+                }
+            }
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches.iter() {
+                    let status = self.execute_list(condition);
+
+                    if self.flow.is_some() {
+                        return status;
+                    }
+
+                    if status == 0 {
+                        return self.execute_list(body);
+                    }
+                }
+
+                match otherwise {
+                    Some(body) => self.execute_list(body),
+                    None => 0,
+                }
+            }
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            } => {
+                let mut status = 0;
+
+                self.loop_depth += 1;
+
+                loop {
+                    let result = self.execute_list(condition);
+
+                    if self.loop_control() || (result == 0) == *until {
+                        break;
+                    }
+
+                    status = self.execute_list(body);
+
+                    if self.loop_control() {
+                        break;
+                    }
+                }
+
+                self.loop_depth -= 1;
+                status
+            }
+            CompoundCommand::For { name, words, body } => {
+                let values = match words {
+                    Some(words) => match self.expand_words(words) {
+                        Some(values) => values,
+                        None => return 1,
+                    },
+                    None => self.args[1..].to_vec(),
+                };
+
+                let mut status = 0;
+
+                self.loop_depth += 1;
+
+                for value in values {
+                    self.set_var(name, value);
+                    status = self.execute_list(body);
+
+                    if self.loop_control() {
+                        break;
+                    }
+                }
+
+                self.loop_depth -= 1;
+                status
+            }
+        }
+    }
+
+    fn loop_control(&mut self) -> bool {
+        match self.flow {
+            Some(Flow::Break(count)) => {
+                self.flow = if count > 1 {
+                    Some(Flow::Break(count - 1))
+                } else {
+                    None
+                };
+                true
+            }
+            Some(Flow::Continue(count)) => {
+                if count > 1 {
+                    self.flow = Some(Flow::Continue(count - 1));
+                    true
+                } else {
+                    self.flow = None;
+                    false
+                }
+            }
+            Some(Flow::Return) | Some(Flow::Interrupt) => true,
+            None => false,
+        }
+    }
+
+    fn execute_simple(&mut self, command: &SimpleCommand, forked: bool) -> i32 {
+        self.substitution_status = None;
+
+        let argv = match self.expand_words(&command.words) {
+            Some(argv) => argv,
+            None => return 1,
+        };
+
+        let mut guard = FdGuard::new();
+
+        if !self.apply_redirects(&command.redirects, &mut guard) {
+            return 1;
+        }
+
+        let mut env = vec![];
+
+        for (name, value) in command.assignments.iter() {
+            match self.expand_word(value) {
+                Some(value) if argv.is_empty() => self.set_var(name, value),
+                Some(value) => env.push((name.clone(), value)),
+                None => return 1,
+            }
+        }
+
+        if argv.is_empty() {
+            return self.substitution_status.unwrap_or(0);
+        }
+
+        let (command, args) = (argv[0].as_str(), &argv[1..]);
+
+        if let Some(body) = self.functions.get(command).cloned() {
+            let saved = self.push_temporary_vars(env);
+            let status = self.call_function(&body, args);
+            self.pop_temporary_vars(saved);
+            return status;
+        }
+
+        if BUILTINS.contains(&command) {
+            let saved = self.push_temporary_vars(env);
+            let status = self.run_builtin(command, args);
+            self.pop_temporary_vars(saved);
+            return status;
+        }
+
+        if forked {
+            return self.exec_external(command, args, &env);
+        }
+
+        self.group = self.new_group(true);
+        let result = self.fork_child(|shell| shell.exec_external(command, args, &env));
+        let group = self.group.take();
+
+        drop(guard);
+
+        match result {
+            Ok(pid) => self.wait_foreground(vec![pid], group, argv.join(" ")),
+            Err(status) => status,
+        }
+    }
+
+    fn call_function(&mut self, body: &Command, args: &[String]) -> i32 {
+        let mut saved = self.args.split_off(1);
+        self.args.extend_from_slice(args);
+
+        self.function_depth += 1;
+        let status = self.execute_command(body, false);
+        self.function_depth -= 1;
+
+        self.args.truncate(1);
+        self.args.append(&mut saved);
+
+        if let Some(Flow::Return) = self.flow {
+            self.flow = None;
+        }
+
+        status
+    }
+
+    fn apply_redirects(&mut self, redirects: &[Redirect], guard: &mut FdGuard) -> bool {
+        for redirect in redirects.iter() {
+            let fd = redirect.fd.unwrap_or(redirect.kind.default_fd());
+            let target = match self.expand_word(&redirect.target) {
+                Some(target) => target,
+                None => return false,
+            };
+
+            if let Err(error) = guard.save(fd) {
+                self.error(format!("sh: {}: {}\n", fd, error));
+                return false;
+            }
+
+            let mut kind = redirect.kind;
+
+            if kind == RedirectKind::DupOutput
+                && redirect.fd.is_none()
+                && target != "-"
+                && target.parse::<i32>().is_err()
+            {
+                kind = RedirectKind::OutputAll;
+            }
+
+            let result = match kind {
+                RedirectKind::DupInput | RedirectKind::DupOutput => {
+                    if target == "-" {
+                        guard.close(fd)
+                    } else {
+                        match target.parse() {
+                            Ok(to) => guard.dup(fd, to),
+                            Err(_) => Err(io::Error::from_raw_os_error(libc::EBADF)),
+                        }
+                    }
+                }
+                RedirectKind::HereString | RedirectKind::HereDoc => {
+                    let content = if kind == RedirectKind::HereString {
+                        format!("{}\n", target)
+                    } else {
+                        target.clone()
+                    };
+
+                    match sys::temp_file(&content) {
+                        Ok(file) => guard.redirect(fd, file),
+                        Err(error) => {
+                            self.error(format!(
+                                "sh: cannot create temp file for here-document: {}\n",
+                                error
+                            ));
+                            return false;
+                        }
+                    }
+                }
+                _ => {
+                    let mut options = File::options();
+
+                    match kind {
+                        RedirectKind::Input => options.read(true),
+                        RedirectKind::ReadWrite => options.read(true).write(true).create(true),
+                        RedirectKind::Output | RedirectKind::OutputAll => {
+                            options.write(true).create(true).truncate(true)
+                        }
+                        _ => options.append(true).create(true),
+                    };
+
+                    match options.open(&target) {
+                        Ok(file) => guard.redirect(fd, file).and_then(|_| match kind {
+                            RedirectKind::OutputAll | RedirectKind::AppendAll => guard.dup(2, fd),
+                            _ => Ok(()),
+                        }),
+                        Err(error) => {
+                            self.error(format!(
+                                "sh: An error occurred while redirecting file {}, error: {}\n",
+                                target, error
+                            ));
+                            return false;
+                        }
+                    }
+                }
+            };
+
+            if result.is_err() {
+                self.error(format!("sh: {}: Bad file descriptor\n", target));
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn external_command(
+        command: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> process::Command {
+        let mut process = process::Command::new(command);
+        process.args(args);
+
+        for (name, value) in env.iter() {
+            process.env(name, value);
+        }
+
+        process
+    }
+
+    fn exec_external(&mut self, command: &str, args: &[String], env: &[(String, String)]) -> i32 {
+        let path = if command.contains('/') {
+            PathBuf::from(command)
+        } else {
+            match find_command(command) {
+                Some(path) => path,
+                None => {
+                    self.error(format!("{}: command not found\n", command));
+                    return 127;
+                }
+            }
+        };
+
+        if !path.exists() {
+            self.error(format!("sh: {}: No such file or directory\n", command));
+            return 127;
+        }
+
+        if path.is_dir() {
+            self.error(format!("sh: {}: Is a directory\n", command));
+            return 126;
+        }
+
+        if !is_executable(&path) {
+            self.error(format!("sh: {}: Permission denied\n", command));
+            return 126;
+        }
+
+        let error = Shell::external_command(command, args, env).exec();
+        self.error(format!("sh: {}: {}\n", command, error));
+
+        match error.kind() {
+            io::ErrorKind::NotFound => 127,
+            _ => 126,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::{parser, test_shell};
+    use std::env;
+    use std::path::PathBuf;
+
+    fn run(input: &str) -> i32 {
+        let mut shell = test_shell();
+        shell.execute_list(&parser::parse(input).unwrap())
+    }
+
+    #[test]
+    fn runs_matching_if_branch() {
+        assert_eq!(run("if false; then true; elif true; then false; fi"), 1);
+        assert_eq!(run("if false; then false; fi"), 0);
+        assert_eq!(run("if true; then true; else false; fi"), 0);
+    }
+
+    #[test]
+    fn breaks_out_of_loops() {
+        assert_eq!(run("while true; do break; done"), 0);
+        assert_eq!(
+            run("until false; do while true; do break 2; done; done; false"),
+            1
+        );
+        assert_eq!(run("false; until true; do false; done"), 0);
+    }
+
+    #[test]
+    fn returns_from_functions() {
+        assert_eq!(run("f() { return 3; true; }; f"), 3);
+        assert_eq!(run("f() { false; }; f"), 1);
+        assert_eq!(run("f() { return; }; false; f"), 0);
+    }
+
+    #[test]
+    fn passes_prefix_assignments_to_commands() {
+        let name = "SIMPLE_SHELL_TEST_PREFIX";
+        let mut shell = test_shell();
+
+        let status = shell
+            .execute_list(&parser::parse(&format!("{}=7 sh -c 'exit ${}'", name, name)).unwrap());
+
+        assert_eq!(status, 7);
+        assert_eq!(shell.get_var(name), None);
+    }
+
+    #[test]
+    fn exports_prefix_assignments_to_functions() {
+        let name = "SIMPLE_SHELL_TEST_FUNCTION";
+        let mut shell = test_shell();
+
+        let status = shell.execute_list(
+            &parser::parse(&format!(
+                "{}=old; f() {{ sh -c 'exit ${}'; }}; {}=5 f",
+                name, name, name
+            ))
+            .unwrap(),
+        );
+
+        assert_eq!(status, 5);
+        assert_eq!(shell.get_var(name).unwrap(), "old");
+        assert!(std::env::var_os(name).is_none());
+    }
+
+    fn redirects(input: &str) -> Vec<Redirect> {
+        match &parser::parse(input).unwrap().items[0].first.commands[0] {
+            Command::Simple(command) => command.redirects.clone(),
+            command => panic!("not a simple command: {:?}", command),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("simple-shell-test-{}-{}", process::id(), name))
+    }
+
+    fn is_open(fd: i32) -> bool {
+        unsafe { libc::fcntl(fd, libc::F_GETFD) != -1 }
+    }
+
+    #[test]
+    fn opens_read_write_redirects() {
+        let path = temp_path("rw");
+        std::fs::write(&path, "abc").unwrap();
+
+        let mut shell = test_shell();
+        let mut guard = FdGuard::new();
+        assert!(shell.apply_redirects(
+            &redirects(&format!("true 57<>{}", path.display())),
+            &mut guard
+        ));
+
+        let mut buffer = [0u8; 3];
+        assert_eq!(unsafe { libc::read(57, buffer.as_mut_ptr().cast(), 3) }, 3);
+        assert_eq!(&buffer, b"abc");
+        assert_eq!(unsafe { libc::write(57, b"Z".as_ptr().cast(), 1) }, 1);
+
+        drop(guard);
+        assert!(!is_open(57));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "abcZ");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn duplicates_and_closes_descriptors() {
+        let path = temp_path("dup");
+
+        let mut shell = test_shell();
+        let mut guard = FdGuard::new();
+        assert!(shell.apply_redirects(
+            &redirects(&format!("true 58<>{} 59>&58 58>&-", path.display())),
+            &mut guard
+        ));
+        assert!(!is_open(58));
+        assert!(is_open(59));
+
+        drop(guard);
+        assert!(!is_open(59));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn redirects_output_and_errors_together() {
+        let path = temp_path("all");
+        let input = format!("true &>{}", path.display());
+
+        let mut shell = test_shell();
+        let pid = shell
+            .fork_child(|shell| {
+                let mut guard = FdGuard::new();
+                if !shell.apply_redirects(&redirects(&input), &mut guard) {
+                    return 1;
+                }
+                unsafe {
+                    libc::write(1, b"out ".as_ptr().cast(), 4);
+                    libc::write(2, b"err".as_ptr().cast(), 3);
+                }
+                0
+            })
+            .unwrap();
+
+        assert_eq!(sys::wait(pid), 0);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "out err");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reports_missing_and_unexecutable_commands() {
+        assert_eq!(run("simple-shell-missing-command 2>/dev/null"), 127);
+        assert_eq!(run("./simple-shell-missing-command 2>/dev/null"), 127);
+        assert_eq!(run("./Cargo.toml 2>/dev/null"), 126);
+        assert_eq!(run("/ 2>/dev/null"), 126);
+        assert_eq!(run("sh -c 'kill -TERM $$'"), 128 + libc::SIGTERM);
+    }
+}
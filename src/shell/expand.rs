@@ -0,0 +1,372 @@
+use home::home_dir;
+
+use super::ast::{Param, ParamOp, Word, WordPart};
+use super::glob::{self, GlobOptions};
+use super::parser::is_name;
+use super::Shell;
+
+#[derive(Default)]
+struct Field {
+    value: String,
+    pattern: String,
+    glob: bool,
+}
+
+struct Fields {
+    list: Vec<Field>,
+    current: Field,
+    present: bool,
+    split: bool,
+}
+
+impl Fields {
+    fn new(split: bool) -> Fields {
+        Fields {
+            list: vec![],
+            current: Field::default(),
+            present: false,
+            split,
+        }
+    }
+
+    fn push_str(&mut self, text: &str, quoted: bool) {
+        self.current.value.push_str(text);
+
+        for ch in text.chars() {
+            if quoted && matches!(ch, '*' | '?' | '[' | ']' | '\\') {
+                self.current.pattern.push('\\');
+            } else if !quoted && matches!(ch, '*' | '?' | '[') {
+                self.current.glob = true;
+            }
+            self.current.pattern.push(ch);
+        }
+
+        self.present = true;
+    }
+
+    fn delimit(&mut self, always: bool) {
+        if !self.split {
+            self.current.value.push(' ');
+        } else if self.present || always {
+            self.list.push(std::mem::take(&mut self.current));
+            self.present = false;
+        }
+    }
+
+    fn finish(mut self) -> Vec<Field> {
+        if self.present {
+            self.list.push(std::mem::take(&mut self.current));
+        }
+        self.list
+    }
+}
+
+impl Shell<'_> {
+    pub fn expand_words(&mut self, words: &[Word]) -> Option<Vec<String>> {
+        let mut result = vec![];
+
+        for word in words.iter() {
+            let mut fields = Fields::new(true);
+            self.expand_tilde(&word.parts, &mut fields)?;
+
+            for field in fields.finish() {
+                if !field.glob || !glob::has_magic(&field.pattern) {
+                    result.push(field.value);
+                    continue;
+                }
+
+                let mut matches = glob::expand(
+                    &field.pattern,
+                    &GlobOptions {
+                        dotglob: self.shopt("dotglob"),
+                        globstar: self.shopt("globstar"),
+                    },
+                );
+
+                if matches.is_empty() {
+                    if self.shopt("failglob") {
+                        self.error(format!("sh: no match: {}\n", field.value));
+                        return None;
+                    }
+                    if !self.shopt("nullglob") {
+                        result.push(field.value);
+                    }
+                } else {
+                    result.append(&mut matches);
+                }
+            }
+        }
+
+        Some(result)
+    }
+
+    pub fn expand_word(&mut self, word: &Word) -> Option<String> {
+        let mut fields = Fields::new(false);
+        self.expand_tilde(&word.parts, &mut fields)?;
+        Some(fields.current.value)
+    }
+
+    fn expand_tilde(&mut self, parts: &[WordPart], fields: &mut Fields) -> Option<()> {
+        if let Some(WordPart::Literal(text)) = parts.first() {
+            let rest = text
+                .strip_prefix('~')
+                .filter(|rest| rest.starts_with('/') || (rest.is_empty() && parts.len() == 1));
+
+            let home = self
+                .get_var("HOME")
+                .or_else(|| home_dir().map(|home| home.to_string_lossy().into_owned()));
+
+            if let (Some(rest), Some(home)) = (rest, home) {
+                fields.push_str(&home, true);
+                fields.push_str(rest, false);
+                return self.expand_parts(&parts[1..], false, fields);
+            }
+        }
+
+        self.expand_parts(parts, false, fields)
+    }
+
+    fn expand_parts(
+        &mut self,
+        parts: &[WordPart],
+        quoted: bool,
+        fields: &mut Fields,
+    ) -> Option<()> {
+        for part in parts.iter() {
+            match part {
+                WordPart::Literal(text) => fields.push_str(text, quoted),
+                WordPart::Quoted(text) => fields.push_str(text, true),
+                WordPart::DoubleQuoted(parts) => {
+                    if !Shell::is_all_args(parts) {
+                        fields.present = true;
+                    }
+                    self.expand_parts(parts, true, fields)?;
+                }
+                WordPart::CommandSubst(list, _) => {
+                    let value = self.capture_output(list)?;
+
+                    if quoted || !fields.split {
+                        fields.push_str(&value, quoted);
+                    } else {
+                        self.split_fields(&value, fields);
+                    }
+                }
+                WordPart::Param(param) => {
+                    if quoted && Shell::is_all_args(std::slice::from_ref(part)) {
+                        for (index, arg) in self.args.iter().skip(1).enumerate() {
+                            if index > 0 {
+                                fields.delimit(true);
+                            }
+                            fields.push_str(arg, true);
+                        }
+                        continue;
+                    }
+
+                    let value = self.expand_param(param)?;
+
+                    if quoted || !fields.split {
+                        fields.push_str(&value, quoted);
+                    } else {
+                        self.split_fields(&value, fields);
+                    }
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    fn is_all_args(parts: &[WordPart]) -> bool {
+        matches!(
+            parts,
+            [WordPart::Param(Param {
+                name,
+                op: ParamOp::Plain,
+            })] if name == "@"
+        )
+    }
+
+    fn split_fields(&self, value: &str, fields: &mut Fields) {
+        let ifs = self.get_var("IFS").unwrap_or_else(|| String::from(" \t\n"));
+
+        for ch in value.chars() {
+            if !ifs.contains(ch) {
+                fields.push_str(ch.encode_utf8(&mut [0; 4]), false);
+            } else if ch.is_ascii_whitespace() {
+                fields.delimit(false);
+            } else {
+                fields.delimit(true);
+            }
+        }
+    }
+
+    fn expand_param(&mut self, param: &Param) -> Option<String> {
+        let value = self.get_param(&param.name);
+
+        let unset = |colon: &bool| {
+            value
+                .as_ref()
+                .map_or(true, |value| *colon && value.is_empty())
+        };
+
+        match &param.op {
+            ParamOp::Plain => Some(value.unwrap_or_default()),
+            ParamOp::Length => Some(value.unwrap_or_default().chars().count().to_string()),
+            ParamOp::Default(colon, word) => {
+                if unset(colon) {
+                    self.expand_word(word)
+                } else {
+                    value
+                }
+            }
+            ParamOp::Assign(colon, word) => {
+                if !unset(colon) {
+                    return value;
+                }
+
+                if !is_name(&param.name) {
+                    self.error(format!("sh: ${}: cannot assign in this way\n", param.name));
+                    return None;
+                }
+
+                let value = self.expand_word(word)?;
+                self.set_var(&param.name, value.clone());
+                Some(value)
+            }
+            ParamOp::Error(colon, word) => {
+                if !unset(colon) {
+                    return value;
+                }
+
+                let mut message = self.expand_word(word)?;
+
+                if message.is_empty() {
+                    message = String::from("parameter null or not set");
+                }
+
+                self.error(format!("sh: {}: {}\n", param.name, message));
+
+                if !self.interactive() {
+                    self.exit(1);
+                }
+
+                None
+            }
+            ParamOp::Alternative(colon, word) => {
+                if unset(colon) {
+                    Some(String::new())
+                } else {
+                    self.expand_word(word)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::ast::Command;
+    use crate::shell::{parser, test_shell, Shell};
+
+    fn run(shell: &mut Shell, input: &str) {
+        shell.execute_list(&parser::parse(input).unwrap());
+    }
+
+    fn expand(shell: &mut Shell, input: &str) -> Vec<String> {
+        let list = parser::parse(input).unwrap();
+
+        match &list.items[0].first.commands[0] {
+            Command::Simple(command) => shell.expand_words(&command.words).unwrap(),
+            command => panic!("not a simple command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn expands_parameter_operators() {
+        let mut shell = test_shell();
+
+        run(
+            &mut shell,
+            "e=; a=${u:-def} b=${e-set} c=${u:=new} d=${a:+alt} f=${e:+alt} n=${#a}",
+        );
+
+        let values: Vec<String> = ["a", "b", "c", "u", "d", "f", "n"]
+            .iter()
+            .map(|name| shell.get_var(name).unwrap())
+            .collect();
+
+        assert_eq!(values, vec!["def", "", "new", "new", "alt", "", "3"]);
+    }
+
+    #[test]
+    fn splits_unquoted_fields() {
+        let mut shell = test_shell();
+
+        run(&mut shell, "y=' a  b '");
+
+        assert_eq!(
+            expand(&mut shell, "echo $y \"$y\" ${y}c"),
+            vec!["echo", "a", "b", " a  b ", "a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn expands_special_parameters() {
+        let mut shell = test_shell();
+
+        run(&mut shell, "false");
+        assert_eq!(
+            expand(&mut shell, "echo $? $# '$?'"),
+            vec!["echo", "1", "0", "$?"]
+        );
+        assert_eq!(expand(&mut shell, "echo \"$@\""), vec!["echo"]);
+    }
+
+    #[test]
+    fn keeps_shell_pid_in_subshells() {
+        let mut shell = test_shell();
+        let pid = std::process::id().to_string();
+
+        assert_eq!(
+            expand(&mut shell, "echo $$ $(echo $$) `(echo $$)`"),
+            vec!["echo", &pid, &pid, &pid]
+        );
+    }
+
+    #[test]
+    fn does_not_split_assignments() {
+        let mut shell = test_shell();
+
+        run(&mut shell, "y='a\tb  c'; x=$y");
+        assert_eq!(shell.get_var("x").unwrap(), "a\tb  c");
+
+        run(&mut shell, "IFS=:; p=a:b:c; x=$p");
+        assert_eq!(shell.get_var("x").unwrap(), "a:b:c");
+    }
+
+    #[test]
+    fn expands_leading_tilde() {
+        let mut shell = test_shell();
+        shell
+            .vars
+            .insert(String::from("HOME"), String::from("/home/test"));
+
+        assert_eq!(
+            expand(&mut shell, "echo ~ ~/x '~' \\~ a~ ~\"/q\""),
+            vec!["echo", "/home/test", "/home/test/x", "~", "~", "a~", "~/q"]
+        );
+    }
+
+    #[test]
+    fn substitutes_command_output() {
+        let mut shell = test_shell();
+
+        assert_eq!(
+            expand(
+                &mut shell,
+                "echo $(printf 'a b\\n\\n') \"$(echo c)\" `echo d`"
+            ),
+            vec!["echo", "a", "b", "c", "d"]
+        );
+    }
+}
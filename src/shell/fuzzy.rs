@@ -0,0 +1,283 @@
+use std::cmp::Reverse;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+
+use super::editor::{char_width, escape, Key};
+use super::sys;
+use super::Shell;
+
+static FILE_LIMIT: usize = 10000;
+
+fn same(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn is_boundary(previous: char, ch: char) -> bool {
+    "/_-. ".contains(previous) || (previous.is_lowercase() && ch.is_uppercase())
+}
+
+pub fn score(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut positions: Vec<usize> = vec![];
+    let mut score = 0;
+    let mut next = 0;
+
+    for wanted in pattern.chars() {
+        let index = (next..chars.len()).find(|index| same(chars[*index], wanted))?;
+
+        score += 16;
+
+        match positions.last() {
+            Some(last) if *last + 1 == index => score += 8,
+            Some(last) => score -= (index - last - 1) as i64,
+            None => score -= index as i64,
+        }
+
+        if index == 0 || is_boundary(chars[index - 1], chars[index]) {
+            score += 10;
+        }
+
+        positions.push(index);
+        next = index + 1;
+    }
+
+    Some((score - (chars.len() / 8) as i64, positions))
+}
+
+pub fn highlight(text: &str, positions: &[usize]) -> String {
+    let mut output = String::new();
+
+    for (index, ch) in text.chars().enumerate() {
+        if positions.contains(&index) {
+            output.push_str(&format!("\x1B[1m{}\x1B[22m", ch));
+        } else {
+            output.push(ch);
+        }
+    }
+
+    output
+}
+
+pub fn complete(word: &str, candidates: Vec<String>) -> Vec<String> {
+    let dir = word.rfind('/').map_or(0, |index| index + 1);
+
+    let mut scored: Vec<(i64, String)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let (score, _) = score(&word[dir..], candidate.get(dir..)?)?;
+            Some((score, candidate))
+        })
+        .collect();
+
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+fn walk_files() -> Vec<String> {
+    let mut results = vec![];
+    let mut queue = VecDeque::from([PathBuf::new()]);
+
+    while let Some(dir) = queue.pop_front() {
+        let Ok(entries) = fs::read_dir(if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir.clone()
+        }) else {
+            continue;
+        };
+
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let path = dir.join(&name);
+            let text = path.to_string_lossy().into_owned();
+
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                results.push(format!("{}/", text));
+                queue.push_back(path);
+            } else {
+                results.push(text);
+            }
+
+            if results.len() >= FILE_LIMIT {
+                return results;
+            }
+        }
+    }
+
+    results
+}
+
+impl Shell<'_> {
+    pub fn pick_file(&mut self) {
+        if let Some(path) = self.pick("files", walk_files()) {
+            self.editor.insert(&format!("{} ", escape(&path)));
+        }
+    }
+
+    pub fn pick_history(&mut self) {
+        let mut seen = HashSet::new();
+        let entries: Vec<String> = self
+            .history
+            .iter()
+            .rev()
+            .filter(|entry| seen.insert(entry.as_str()))
+            .cloned()
+            .collect();
+
+        if let Some(entry) = self.pick("history", entries) {
+            self.editor.buffer = entry.chars().collect();
+            self.editor.cursor = self.editor.buffer.len();
+        }
+    }
+
+    fn pick(&mut self, title: &str, items: Vec<String>) -> Option<String> {
+        let mut query = String::new();
+        let mut selected = 0;
+        let mut offset = 0;
+
+        self.output("\x1B[?1049h");
+
+        let choice = loop {
+            let mut matches: Vec<(i64, usize, Vec<usize>)> = items
+                .iter()
+                .enumerate()
+                .filter_map(|(index, item)| {
+                    score(&query, item).map(|(score, positions)| (score, index, positions))
+                })
+                .collect();
+
+            if !query.is_empty() {
+                matches.sort_by_key(|(score, _, _)| Reverse(*score));
+            }
+
+            selected = selected.min(matches.len().saturating_sub(1));
+
+            let (width, height) = sys::terminal_size();
+            let visible = height.saturating_sub(2).max(1);
+
+            if selected < offset {
+                offset = selected;
+            } else if selected >= offset + visible {
+                offset = selected + 1 - visible;
+            }
+
+            let mut output = format!(
+                "\x1B[H\x1B[2J{}> {}\r\n  {}/{}",
+                title,
+                query,
+                matches.len(),
+                items.len()
+            );
+
+            for (row, (_, index, positions)) in
+                matches.iter().enumerate().skip(offset).take(visible)
+            {
+                let mut text = String::new();
+                let mut used = 2;
+
+                for ch in items[*index].chars() {
+                    let ch = if ch == '\n' { ' ' } else { ch };
+                    used += char_width(ch);
+                    if used > width {
+                        break;
+                    }
+                    text.push(ch);
+                }
+
+                let line = highlight(&text, positions);
+
+                if row == selected {
+                    output.push_str(&format!("\r\n\x1B[7m> {}\x1B[0m", line));
+                } else {
+                    output.push_str(&format!("\r\n  {}", line));
+                }
+            }
+
+            let column: usize = title.chars().chain(query.chars()).map(char_width).sum();
+            output.push_str(&format!("\x1B[1;{}H", column + 3));
+            self.output(output);
+
+            match self.read_key() {
+                Some(Key::Char(ch)) => {
+                    query.push(ch);
+                    selected = 0;
+                }
+                Some(Key::Backspace | Key::Ctrl('h')) => {
+                    query.pop();
+                    selected = 0;
+                }
+                Some(Key::Up | Key::Ctrl('p') | Key::BackTab) => {
+                    selected = selected.saturating_sub(1);
+                }
+                Some(Key::Down | Key::Ctrl('n') | Key::Tab) => selected += 1,
+                Some(Key::Enter) => {
+                    break matches
+                        .get(selected)
+                        .map(|(_, index, _)| items[*index].clone());
+                }
+                Some(Key::Escape | Key::Ctrl('c' | 'g' | 'd')) | None => break None,
+                Some(_) => {}
+            }
+        };
+
+        self.output("\x1B[?1049l");
+
+        choice
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_subsequences() {
+        assert_eq!(
+            score("mr", "main.rs").map(|(_, positions)| positions),
+            Some(vec![0, 5])
+        );
+        assert!(score("rm", "main.rs").is_none());
+        assert!(score("", "anything").is_some());
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert!(score("MAIN", "main.rs").is_some());
+        assert!(score("readme", "README.md").is_some());
+    }
+
+    #[test]
+    fn prefers_consecutive_and_boundary_matches() {
+        let score_of = |pattern, text| score(pattern, text).unwrap().0;
+
+        assert!(score_of("main", "main.rs") > score_of("main", "my_domain.rs"));
+        assert!(score_of("fb", "foo_bar") > score_of("fb", "afxbx"));
+        assert!(score_of("sh", "src/shell") > score_of("sh", "src/fresh"));
+    }
+
+    #[test]
+    fn ranks_completions() {
+        let candidates = vec![String::from("src/lib_mod.rs"), String::from("src/mod.rs")];
+
+        assert_eq!(
+            complete("src/mod", candidates),
+            vec![String::from("src/mod.rs"), String::from("src/lib_mod.rs")]
+        );
+    }
+
+    #[test]
+    fn highlights_positions() {
+        assert_eq!(highlight("ab", &[1]), "a\x1B[1mb\x1B[22m");
+    }
+}
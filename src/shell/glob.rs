@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::Path;
+
+pub struct GlobOptions {
+    pub dotglob: bool,
+    pub globstar: bool,
+}
+
+pub fn has_magic(pattern: &str) -> bool {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut index = 0;
+
+    while index < chars.len() {
+        match chars[index] {
+            '\\' => index += 1,
+            '*' | '?' => return true,
+            '[' if bracket(&chars[index..], '\0').is_some() => return true,
+            _ => {}
+        }
+        index += 1;
+    }
+
+    false
+}
+
+pub fn unescape(pattern: &str) -> String {
+    let mut result = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            result.extend(chars.next());
+        } else {
+            result.push(ch);
+        }
+    }
+
+    result
+}
+
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            if pattern[p] == '*' {
+                star = Some((p, t));
+                p += 1;
+                continue;
+            }
+
+            if let Some(length) = match_one(&pattern[p..], text[t]) {
+                p += length;
+                t += 1;
+                continue;
+            }
+        }
+
+        match star {
+            Some((star_p, star_t)) => {
+                p = star_p + 1;
+                t = star_t + 1;
+                star = Some((star_p, star_t + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+fn match_one(pattern: &[char], ch: char) -> Option<usize> {
+    match pattern[0] {
+        '?' => Some(1),
+        '[' => match bracket(pattern, ch) {
+            Some((true, length)) => Some(length),
+            Some((false, _)) => None,
+            None => (ch == '[').then_some(1),
+        },
+        '\\' if pattern.len() > 1 => (pattern[1] == ch).then_some(2),
+        expected => (expected == ch).then_some(1),
+    }
+}
+
+fn bracket(pattern: &[char], ch: char) -> Option<(bool, usize)> {
+    let mut index = 1;
+    let negate = matches!(pattern.get(index), Some('!') | Some('^'));
+
+    if negate {
+        index += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        let current = *pattern.get(index)?;
+
+        if current == ']' && !first {
+            return Some((matched != negate, index + 1));
+        }
+
+        first = false;
+
+        if current == '[' && pattern.get(index + 1) == Some(&':') {
+            let rest: String = pattern[index + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                matched |= class_matches(&rest[..end], ch);
+                index += 2 + rest[..end].chars().count() + 2;
+                continue;
+            }
+        }
+
+        let (low, length) = match current {
+            '\\' => (*pattern.get(index + 1)?, 2),
+            current => (current, 1),
+        };
+
+        index += length;
+
+        if pattern.get(index) == Some(&'-') && pattern.get(index + 1).is_some_and(|ch| *ch != ']') {
+            let (high, length) = match pattern[index + 1] {
+                '\\' => (*pattern.get(index + 2)?, 3),
+                high => (high, 2),
+            };
+            index += length;
+            matched |= low <= ch && ch <= high;
+        } else {
+            matched |= low == ch;
+        }
+    }
+}
+
+fn class_matches(class: &str, ch: char) -> bool {
+    match class {
+        "alpha" => ch.is_alphabetic(),
+        "digit" => ch.is_ascii_digit(),
+        "alnum" => ch.is_alphanumeric(),
+        "upper" => ch.is_uppercase(),
+        "lower" => ch.is_lowercase(),
+        "space" => ch.is_whitespace(),
+        "punct" => ch.is_ascii_punctuation(),
+        "xdigit" => ch.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+pub fn expand(pattern: &str, options: &GlobOptions) -> Vec<String> {
+    let directories_only = pattern.ends_with('/');
+    let components: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+
+    let mut paths = vec![if pattern.starts_with('/') {
+        String::from("/")
+    } else {
+        String::new()
+    }];
+
+    for (index, component) in components.iter().enumerate() {
+        let last = index + 1 == components.len();
+        let mut next = vec![];
+
+        for base in paths.iter() {
+            if options.globstar && *component == "**" {
+                if !last {
+                    next.push(base.clone());
+                }
+                walk(base, last, options, &mut next);
+            } else if !has_magic(component) {
+                let path = join(base, &unescape(component));
+                let path_ref = Path::new(&path);
+                if (last && path_ref.symlink_metadata().is_ok()) || path_ref.is_dir() {
+                    next.push(path);
+                }
+            } else {
+                let hidden = options.dotglob || component.starts_with('.');
+
+                for name in read_dir(base) {
+                    if name.starts_with('.') && !hidden {
+                        continue;
+                    }
+
+                    if !matches(component, &name) {
+                        continue;
+                    }
+
+                    let path = join(base, &name);
+
+                    if last || Path::new(&path).is_dir() {
+                        next.push(path);
+                    }
+                }
+            }
+        }
+
+        paths = next;
+    }
+
+    if components.is_empty() {
+        return vec![];
+    }
+
+    if directories_only {
+        paths = paths
+            .into_iter()
+            .filter(|path| Path::new(path).is_dir())
+            .map(|path| format!("{}/", path))
+            .collect();
+    }
+
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn walk(base: &str, files: bool, options: &GlobOptions, result: &mut Vec<String>) {
+    for name in read_dir(base) {
+        if name.starts_with('.') && !options.dotglob {
+            continue;
+        }
+
+        let path = join(base, &name);
+        let is_dir = fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir());
+
+        if is_dir || files {
+            result.push(path.clone());
+        }
+
+        if is_dir {
+            walk(&path, files, options, result);
+        }
+    }
+}
+
+fn read_dir(base: &str) -> Vec<String> {
+    let directory = if base.is_empty() { "." } else { base };
+
+    match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+fn join(base: &str, name: &str) -> String {
+    if base.is_empty() {
+        name.to_owned()
+    } else if base.ends_with('/') {
+        format!("{}{}", base, name)
+    } else {
+        format!("{}/{}", base, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(matches("?", "x"));
+        assert!(!matches("?", ""));
+        assert!(!matches("*.rs", "main.rsx"));
+        assert!(matches("**", "a"));
+    }
+
+    #[test]
+    fn matches_brackets() {
+        assert!(matches("[abc]", "b"));
+        assert!(!matches("[abc]", "d"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(matches("[!a-c]", "d"));
+        assert!(!matches("[!a-c]", "a"));
+        assert!(matches("[[:digit:]]", "7"));
+        assert!(matches("[]]", "]"));
+    }
+
+    #[test]
+    fn honors_escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(!has_magic("\\*.rs"));
+        assert!(has_magic("*.rs"));
+        assert!(!has_magic("[unclosed"));
+        assert_eq!(unescape("a\\*b"), "a*b");
+    }
+
+    #[test]
+    fn expands_paths() {
+        let root = env::temp_dir().join(format!("simple-shell-test-{}-glob", process::id()));
+        fs::create_dir_all(root.join("src/shell")).unwrap();
+        for file in ["a.rs", ".hidden.rs", "src/b.rs", "src/shell/c.rs"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let root_str = root.to_str().unwrap();
+        let names = |pattern: &str, dotglob: bool, globstar: bool| -> Vec<String> {
+            expand(
+                &format!("{}/{}", root_str, pattern),
+                &GlobOptions { dotglob, globstar },
+            )
+            .into_iter()
+            .map(|path| path[root_str.len() + 1..].to_owned())
+            .collect()
+        };
+
+        assert_eq!(names("*.rs", false, false), vec!["a.rs"]);
+        assert_eq!(names("*.rs", true, false), vec![".hidden.rs", "a.rs"]);
+        assert_eq!(names("*/", false, false), vec!["src/"]);
+        assert_eq!(names("**/*.rs", false, false), vec!["src/b.rs"]);
+        assert_eq!(
+            names("**/*.rs", false, true),
+            vec!["a.rs", "src/b.rs", "src/shell/c.rs"]
+        );
+        assert!(names("*.txt", false, false).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
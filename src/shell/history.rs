@@ -0,0 +1,706 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use home::home_dir;
+
+use super::editor::Key;
+use super::parser;
+use super::sys;
+use super::Shell;
+use crate::resolve_path;
+
+static DEFAULT_HISTSIZE: usize = 500;
+
+fn parse(text: &str) -> Vec<String> {
+    let mut entries = vec![];
+    let mut entry = String::new();
+
+    for line in text.lines() {
+        if entry.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+
+        if !entry.is_empty() {
+            entry.push('\n');
+        }
+        entry.push_str(line);
+
+        if parser::is_complete(&format!("{}\n", entry)) {
+            entries.push(std::mem::take(&mut entry));
+        }
+    }
+
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+
+    entries
+}
+
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = line.chars();
+
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (None, ch) if ch.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            (None, '\'' | '"') => quote = Some(ch),
+            (Some(open), ch) if ch == open => quote = None,
+            (quote, '\\') if quote != Some('\'') => {
+                word.push(ch);
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        word.push(ch);
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+fn is_event_end(ch: char) -> bool {
+    ch.is_whitespace() || ":;&|<>()\"'$^*".contains(ch)
+}
+
+impl Shell<'_> {
+    fn history_file(&self) -> Option<PathBuf> {
+        match self.get_var("HISTFILE") {
+            Some(path) if path.is_empty() => None,
+            Some(path) => Some(resolve_path(&path)),
+            None => home_dir().map(|home| home.join(".simple_shell_history")),
+        }
+    }
+
+    fn history_limit(&self, name: &str, default: usize) -> usize {
+        self.get_var(name)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    pub fn load_history(&mut self) {
+        let Some(text) = self.history_file().and_then(|path| fs::read(path).ok()) else {
+            return;
+        };
+
+        self.history = parse(&String::from_utf8_lossy(&text));
+        self.trim_history();
+    }
+
+    fn trim_history(&mut self) {
+        let size = self.history_limit("HISTSIZE", DEFAULT_HISTSIZE);
+
+        if self.history.len() > size {
+            self.history.drain(..self.history.len() - size);
+        }
+    }
+
+    pub fn add_history(&mut self, input: &str) {
+        let entry = input.trim_end_matches('\n');
+
+        if entry.trim().is_empty() {
+            return;
+        }
+
+        self.history.push(entry.to_owned());
+        self.trim_history();
+
+        if let Some(path) = self.history_file() {
+            if let Err(error) = self.append_history(&path, entry) {
+                self.error(format!("sh: {}: {}\n", path.display(), error));
+            }
+        }
+    }
+
+    fn append_history(&self, path: &Path, entry: &str) -> io::Result<()> {
+        let mut file = File::options()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        sys::lock(&file)?;
+        file.write_all(format!("{}\n", entry).as_bytes())?;
+
+        let size = self.history_limit(
+            "HISTFILESIZE",
+            self.history_limit("HISTSIZE", DEFAULT_HISTSIZE),
+        );
+
+        let mut text = vec![];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut text)?;
+
+        let entries = parse(&String::from_utf8_lossy(&text));
+
+        if entries.len() > size {
+            let kept: String = entries[entries.len() - size..]
+                .iter()
+                .map(|entry| format!("{}\n", entry))
+                .collect();
+
+            file.set_len(0)?;
+            file.write_all(kept.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn history_builtin(&mut self, argv: &[String]) -> i32 {
+        match argv.first().map(String::as_str) {
+            Some("-c") => {
+                self.history.clear();
+                0
+            }
+            Some("-d") => {
+                let position = argv
+                    .get(1)
+                    .and_then(|arg| arg.parse::<usize>().ok())
+                    .filter(|position| *position > 0 && *position <= self.history.len());
+
+                match position {
+                    Some(position) => {
+                        self.history.remove(position - 1);
+                        0
+                    }
+                    None => {
+                        self.error(format!(
+                            "history: {}: history position out of range\n",
+                            argv.get(1).map_or("", String::as_str)
+                        ));
+                        1
+                    }
+                }
+            }
+            Some(option @ ("-w" | "-r")) => {
+                let Some(path) = argv
+                    .get(1)
+                    .map(|path| resolve_path(path))
+                    .or_else(|| self.history_file())
+                else {
+                    return 0;
+                };
+
+                let result = if option == "-w" {
+                    let text: String = self
+                        .history
+                        .iter()
+                        .map(|entry| format!("{}\n", entry))
+                        .collect();
+                    fs::write(&path, text)
+                } else {
+                    fs::read(&path).map(|text| {
+                        self.history.extend(parse(&String::from_utf8_lossy(&text)));
+                        self.trim_history();
+                    })
+                };
+
+                if let Err(error) = result {
+                    self.error(format!("history: {}: {}\n", path.display(), error));
+                    return 1;
+                }
+
+                0
+            }
+            Some(option) if option.starts_with('-') => {
+                self.error(format!("history: {}: invalid option\n", option));
+                2
+            }
+            count => {
+                let count = match count.map(|count| count.parse::<usize>()) {
+                    None => self.history.len(),
+                    Some(Ok(count)) => count.min(self.history.len()),
+                    Some(Err(_)) => {
+                        self.error(format!("history: {}: numeric argument required\n", argv[0]));
+                        return 1;
+                    }
+                };
+
+                let output: String = self
+                    .history
+                    .iter()
+                    .enumerate()
+                    .skip(self.history.len() - count)
+                    .map(|(index, entry)| format!("{:>5}  {}\n", index + 1, entry))
+                    .collect();
+
+                self.output(output);
+                0
+            }
+        }
+    }
+
+    pub fn history_previous(&mut self) {
+        if self.editor.history_index == 0 {
+            return;
+        }
+
+        if self.editor.history_index == self.history.len() {
+            self.editor.draft = self.editor.buffer.clone();
+        }
+
+        self.editor.history_index -= 1;
+        self.editor.buffer = self.history[self.editor.history_index].chars().collect();
+        self.editor.cursor = self.editor.buffer.len();
+    }
+
+    pub fn history_next(&mut self) {
+        if self.editor.history_index >= self.history.len() {
+            return;
+        }
+
+        self.editor.history_index += 1;
+        self.editor.buffer = match self.history.get(self.editor.history_index) {
+            Some(entry) => entry.chars().collect(),
+            None => std::mem::take(&mut self.editor.draft),
+        };
+        self.editor.cursor = self.editor.buffer.len();
+    }
+
+    pub fn search_history(&mut self, reverse: bool) -> Option<Key> {
+        let original = (
+            self.editor.buffer.clone(),
+            self.editor.cursor,
+            self.editor.history_index,
+        );
+
+        let mut reverse = reverse;
+        let mut query = String::new();
+        let mut found = true;
+
+        loop {
+            self.editor.search = Some(format!(
+                "({}{}i-search)`{}': ",
+                if found { "" } else { "failed " },
+                if reverse { "reverse-" } else { "" },
+                query
+            ));
+            self.refresh();
+
+            let key = self.read_key();
+            let current = self.editor.history_index;
+
+            found = match key {
+                Some(Key::Char(ch)) => {
+                    query.push(ch);
+                    self.search_step(&query, reverse, current)
+                }
+                Some(Key::Backspace | Key::Ctrl('h')) => {
+                    query.pop();
+                    self.search_step(&query, reverse, current)
+                }
+                Some(Key::Ctrl('r')) => {
+                    reverse = true;
+                    current > 0 && self.search_step(&query, reverse, current - 1)
+                }
+                Some(Key::Ctrl('s')) => {
+                    reverse = false;
+                    self.search_step(&query, reverse, current + 1)
+                }
+                Some(Key::Ctrl('g')) => {
+                    (
+                        self.editor.buffer,
+                        self.editor.cursor,
+                        self.editor.history_index,
+                    ) = original;
+                    self.editor.search = None;
+                    return Some(Key::Unknown);
+                }
+                key => {
+                    self.editor.search = None;
+                    return key;
+                }
+            };
+        }
+    }
+
+    fn search_step(&mut self, query: &str, reverse: bool, from: usize) -> bool {
+        if query.is_empty() {
+            return true;
+        }
+
+        let indexes: Vec<usize> = if reverse {
+            (0..(from + 1).min(self.history.len())).rev().collect()
+        } else {
+            (from..self.history.len()).collect()
+        };
+
+        for index in indexes {
+            if let Some(position) = self.history[index].find(query) {
+                if self.editor.history_index == self.history.len() {
+                    self.editor.draft = self.editor.buffer.clone();
+                }
+
+                self.editor.history_index = index;
+                self.editor.buffer = self.history[index].chars().collect();
+                self.editor.cursor = self.history[index][..position].chars().count();
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn expand_history(&self, line: &str) -> Result<(String, bool), String> {
+        if let Some(rest) = line.strip_prefix('^') {
+            return self.quick_substitution(rest).map(|line| (line, true));
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut output = String::new();
+        let mut expanded = false;
+        let mut quote = None;
+        let mut index = 0;
+
+        while index < chars.len() {
+            let ch = chars[index];
+
+            match (quote, ch) {
+                (None, '\'' | '"') => quote = Some(ch),
+                (Some(open), ch) if ch == open => quote = None,
+                (quote, '\\') if quote != Some('\'') && index + 1 < chars.len() => {
+                    output.push(ch);
+                    output.push(chars[index + 1]);
+                    index += 2;
+                    continue;
+                }
+                (quote, '!') if quote != Some('\'') => {
+                    let literal = match chars.get(index + 1) {
+                        None => true,
+                        Some(next) => {
+                            next.is_whitespace()
+                                || matches!(next, '=' | '(')
+                                || (*next == '"' && quote.is_some())
+                        }
+                    };
+
+                    if !literal && (index == 0 || chars[index - 1] != '$') {
+                        let (text, next) = self.history_event(&chars, index + 1)?;
+                        output.push_str(&text);
+                        expanded = true;
+                        index = next;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            output.push(ch);
+            index += 1;
+        }
+
+        Ok((output, expanded))
+    }
+
+    fn history_event(&self, chars: &[char], start: usize) -> Result<(String, usize), String> {
+        let mut index = start;
+
+        let entry = match chars[index] {
+            '!' => {
+                index += 1;
+                self.history.last()
+            }
+            '$' | '^' | '*' | ':' => self.history.last(),
+            '?' => {
+                index += 1;
+                while index < chars.len() && chars[index] != '?' && chars[index] != '\n' {
+                    index += 1;
+                }
+                let text: String = chars[start + 1..index].iter().collect();
+                if index < chars.len() && chars[index] == '?' {
+                    index += 1;
+                }
+                self.history
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.contains(&text))
+            }
+            '-' | '0'..='9' => {
+                index += 1;
+                while index < chars.len() && chars[index].is_ascii_digit() {
+                    index += 1;
+                }
+                let number: String = chars[start..index].iter().collect();
+                match number.parse::<isize>() {
+                    Ok(number) if number < 0 => self
+                        .history
+                        .len()
+                        .checked_sub(number.unsigned_abs())
+                        .and_then(|index| self.history.get(index)),
+                    Ok(number) if number > 0 => self.history.get(number as usize - 1),
+                    _ => None,
+                }
+            }
+            _ => {
+                while index < chars.len() && !is_event_end(chars[index]) {
+                    index += 1;
+                }
+                let prefix: String = chars[start..index].iter().collect();
+                self.history
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.starts_with(&prefix))
+            }
+        };
+
+        let event: String = chars[start - 1..index].iter().collect();
+
+        let Some(entry) = entry else {
+            return Err(format!("{}: event not found", event));
+        };
+
+        let designator = match chars.get(index) {
+            Some(':') => {
+                index += 1;
+                true
+            }
+            Some('^' | '$' | '*') => true,
+            _ => false,
+        };
+
+        if !designator {
+            return Ok((entry.clone(), index));
+        }
+
+        let words = split_words(entry);
+        let last = words.len().saturating_sub(1);
+
+        let term = |index: &mut usize| -> Option<usize> {
+            match chars.get(*index) {
+                Some('^') => {
+                    *index += 1;
+                    Some(1)
+                }
+                Some('$') => {
+                    *index += 1;
+                    Some(last)
+                }
+                Some(ch) if ch.is_ascii_digit() => {
+                    let from = *index;
+                    while *index < chars.len() && chars[*index].is_ascii_digit() {
+                        *index += 1;
+                    }
+                    chars[from..*index].iter().collect::<String>().parse().ok()
+                }
+                _ => None,
+            }
+        };
+
+        let range = if chars.get(index) == Some(&'*') {
+            index += 1;
+            Some((1, last))
+        } else {
+            let first = term(&mut index);
+
+            match chars.get(index) {
+                Some('*') => {
+                    index += 1;
+                    first.map(|first| (first, last))
+                }
+                Some('-') => {
+                    index += 1;
+                    let first = first.unwrap_or(0);
+                    match term(&mut index) {
+                        Some(end) => Some((first, end)),
+                        None => Some((first, last.saturating_sub(1))),
+                    }
+                }
+                _ => first.map(|first| (first, first)),
+            }
+        };
+
+        let text = match range {
+            Some((first, end)) if first > end && end + 1 == first => Some(String::new()),
+            Some((first, end)) if first <= end && end < words.len() => {
+                Some(words[first..=end].join(" "))
+            }
+            _ => None,
+        };
+
+        match text {
+            Some(text) => Ok((text, index)),
+            None => {
+                let specifier: String = chars[start - 1..index].iter().collect();
+                Err(format!("{}: bad word specifier", specifier))
+            }
+        }
+    }
+
+    fn quick_substitution(&self, text: &str) -> Result<String, String> {
+        let mut parts = text.splitn(3, '^');
+        let old = parts.next().unwrap_or_default().trim_end_matches('\n');
+        let new = parts.next().unwrap_or_default().trim_end_matches('\n');
+        let rest = parts.next().unwrap_or("\n");
+
+        let Some(entry) = self.history.last() else {
+            return Err(String::from("^: event not found"));
+        };
+
+        if old.is_empty() || !entry.contains(old) {
+            return Err(format!(":s^{}^{}^: substitution failed", old, new));
+        }
+
+        Ok(format!("{}{}", entry.replacen(old, new, 1), rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::test_shell;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn parses_multiline_entries() {
+        assert_eq!(
+            parse("echo one\nif true\nthen echo two\nfi\n\necho three\n"),
+            vec!["echo one", "if true\nthen echo two\nfi", "echo three"]
+        );
+    }
+
+    #[test]
+    fn trims_history_and_file() {
+        let path = env::temp_dir().join(format!("simple-shell-test-{}-history", process::id()));
+
+        let mut shell = test_shell();
+        shell.set_var("HISTFILE", path.to_string_lossy().into_owned());
+        shell.set_var("HISTSIZE", String::from("2"));
+        shell.set_var("HISTFILESIZE", String::from("3"));
+
+        for input in [
+            "echo one\n",
+            "  \n",
+            "for a in b\ndo echo $a\ndone\n",
+            "ls\n",
+            "pwd\n",
+        ] {
+            shell.add_history(input);
+        }
+
+        assert_eq!(shell.history, vec!["ls", "pwd"]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "for a in b\ndo echo $a\ndone\nls\npwd\n"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn navigates_history() {
+        let mut shell = test_shell();
+        shell.history = vec![String::from("echo one"), String::from("ls")];
+        shell.editor.history_index = 2;
+        shell.editor.insert("draft");
+
+        shell.history_previous();
+        shell.history_previous();
+        shell.history_previous();
+        assert_eq!(shell.editor.history_index, 0);
+        assert_eq!(shell.editor.buffer.iter().collect::<String>(), "echo one");
+
+        shell.history_next();
+        shell.history_next();
+        assert_eq!(shell.editor.buffer.iter().collect::<String>(), "draft");
+        assert_eq!(shell.editor.cursor, 5);
+    }
+
+    #[test]
+    fn searches_history() {
+        let mut shell = test_shell();
+        shell.history = vec![
+            String::from("echo one"),
+            String::from("ls"),
+            String::from("echo two"),
+        ];
+        shell.editor.history_index = 3;
+
+        assert!(shell.search_step("echo", true, 2));
+        assert_eq!((shell.editor.history_index, shell.editor.cursor), (2, 0));
+
+        assert!(shell.search_step("one", true, 1));
+        assert_eq!((shell.editor.history_index, shell.editor.cursor), (0, 5));
+
+        assert!(shell.search_step("o", false, 1));
+        assert_eq!(shell.editor.history_index, 2);
+
+        assert!(!shell.search_step("ls", false, 2));
+        assert!(!shell.search_step("missing", true, 2));
+    }
+
+    fn expand(history: &[&str], line: &str) -> Result<String, String> {
+        let mut shell = test_shell();
+        shell.history = history.iter().map(|entry| entry.to_string()).collect();
+        shell.expand_history(line).map(|(line, _)| line)
+    }
+
+    #[test]
+    fn expands_events() {
+        let history = ["echo one", "ls -la /tmp", "echo two"];
+
+        assert_eq!(expand(&history, "!!").unwrap(), "echo two");
+        assert_eq!(expand(&history, "!1").unwrap(), "echo one");
+        assert_eq!(expand(&history, "!-2").unwrap(), "ls -la /tmp");
+        assert_eq!(expand(&history, "!ls").unwrap(), "ls -la /tmp");
+        assert_eq!(expand(&history, "!?one?").unwrap(), "echo one");
+        assert_eq!(
+            expand(&history, "!nope").unwrap_err(),
+            "!nope: event not found"
+        );
+    }
+
+    #[test]
+    fn expands_word_designators() {
+        let history = ["cp 'a b' c d"];
+
+        assert_eq!(expand(&history, "echo !$").unwrap(), "echo d");
+        assert_eq!(expand(&history, "echo !^").unwrap(), "echo 'a b'");
+        assert_eq!(expand(&history, "echo !*").unwrap(), "echo 'a b' c d");
+        assert_eq!(expand(&history, "echo !!:0").unwrap(), "echo cp");
+        assert_eq!(expand(&history, "echo !!:2-3").unwrap(), "echo c d");
+        assert!(expand(&history, "echo !!:9").is_err());
+    }
+
+    #[test]
+    fn leaves_literal_bangs() {
+        let history = ["echo one"];
+
+        assert_eq!(expand(&history, "echo '!!'").unwrap(), "echo '!!'");
+        assert_eq!(expand(&history, "echo \\!!").unwrap(), "echo \\!!");
+        assert_eq!(expand(&history, "echo ! x").unwrap(), "echo ! x");
+        assert_eq!(expand(&history, "echo $!").unwrap(), "echo $!");
+    }
+
+    #[test]
+    fn substitutes_quickly() {
+        let history = ["cat flie.txt"];
+
+        assert_eq!(expand(&history, "^flie^file\n").unwrap(), "cat file.txt\n");
+        assert!(expand(&history, "^nothing^x").is_err());
+    }
+
+    #[test]
+    fn splits_history_words() {
+        assert_eq!(
+            split_words("echo 'a b' \"c d\" e\\ f"),
+            vec!["echo", "'a b'", "\"c d\"", "e\\ f"]
+        );
+    }
+}
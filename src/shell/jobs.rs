@@ -0,0 +1,458 @@
+use libc::{pid_t, STDIN_FILENO};
+
+use super::sys;
+use super::Shell;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Running,
+    Stopped,
+    Done(i32),
+}
+
+pub struct Job {
+    id: usize,
+    pgid: pid_t,
+    processes: Vec<(pid_t, State)>,
+    command: String,
+    reported: State,
+}
+
+pub struct ProcessGroup {
+    pub pgid: pid_t,
+    pub foreground: bool,
+}
+
+pub fn init() -> bool {
+    unsafe {
+        if libc::isatty(STDIN_FILENO) != 1 {
+            return false;
+        }
+
+        while libc::tcgetpgrp(STDIN_FILENO) != libc::getpgrp() {
+            libc::kill(-libc::getpgrp(), libc::SIGTTIN);
+        }
+
+        for signal in [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU] {
+            libc::signal(signal, libc::SIG_IGN);
+        }
+
+        let pid = libc::getpid();
+
+        if libc::getpgrp() != pid && libc::setpgid(0, pid) != 0 {
+            return false;
+        }
+
+        libc::tcsetpgrp(STDIN_FILENO, pid) == 0
+    }
+}
+
+impl Job {
+    fn state(&self) -> State {
+        if self
+            .processes
+            .iter()
+            .any(|(_, state)| *state == State::Stopped)
+        {
+            State::Stopped
+        } else if self
+            .processes
+            .iter()
+            .any(|(_, state)| *state == State::Running)
+        {
+            State::Running
+        } else {
+            self.processes
+                .last()
+                .map_or(State::Done(0), |(_, state)| *state)
+        }
+    }
+
+    fn update(&mut self, pid: pid_t, status: i32) {
+        let state = if libc::WIFSTOPPED(status) {
+            State::Stopped
+        } else if libc::WIFCONTINUED(status) {
+            State::Running
+        } else {
+            State::Done(status)
+        };
+
+        for process in self.processes.iter_mut().filter(|(id, _)| *id == pid) {
+            process.1 = state;
+        }
+    }
+
+    fn wait(&mut self) {
+        for index in 0..self.processes.len() {
+            let pid = self.processes[index].0;
+
+            while self.processes[index].1 == State::Running {
+                match sys::wait_pid(pid, libc::WUNTRACED) {
+                    Some(status) => self.update(pid, status),
+                    None => self.processes[index].1 = State::Done(0),
+                }
+            }
+
+            if self.processes[index].1 == State::Stopped {
+                return;
+            }
+        }
+    }
+
+    fn poll(&mut self) {
+        for index in 0..self.processes.len() {
+            let pid = self.processes[index].0;
+
+            while !matches!(self.processes[index].1, State::Done(_)) {
+                match sys::wait_pid(pid, libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED) {
+                    Some(status) => self.update(pid, status),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    fn resume(&mut self) {
+        unsafe { libc::kill(-self.pgid, libc::SIGCONT) };
+
+        for process in self.processes.iter_mut() {
+            if process.1 == State::Stopped {
+                process.1 = State::Running;
+            }
+        }
+
+        self.reported = State::Running;
+    }
+}
+
+impl Shell<'_> {
+    pub fn wait_foreground(
+        &mut self,
+        pids: Vec<pid_t>,
+        group: Option<ProcessGroup>,
+        command: String,
+    ) -> i32 {
+        let job = Job {
+            id: 0,
+            pgid: group.map_or(0, |group| group.pgid),
+            processes: pids.into_iter().map(|pid| (pid, State::Running)).collect(),
+            command,
+            reported: State::Running,
+        };
+
+        self.wait_job(job, false)
+    }
+
+    fn wait_job(&mut self, mut job: Job, resume: bool) -> i32 {
+        if job.pgid != 0 {
+            unsafe { libc::tcsetpgrp(STDIN_FILENO, job.pgid) };
+        }
+
+        if resume {
+            job.resume();
+        }
+
+        job.wait();
+
+        if job.pgid != 0 {
+            unsafe { libc::tcsetpgrp(STDIN_FILENO, libc::getpgrp()) };
+        }
+
+        match job.state() {
+            State::Done(status) => {
+                match libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status)) {
+                    Some(libc::SIGINT) => {
+                        sys::interrupt();
+                        if self.interactive() {
+                            self.output("\n");
+                        }
+                    }
+                    Some(libc::SIGPIPE) | None => {}
+                    Some(_) => {
+                        let message = sys::signal_message(status).unwrap_or_default();
+                        self.error(format!("{}\n", message));
+                    }
+                }
+                sys::exit_code(status)
+            }
+            state => {
+                if job.id == 0 {
+                    job.id = self.next_job_id();
+                }
+
+                job.reported = state;
+                self.jobs.push(job);
+                self.error(format!("\n{}", self.format_job(self.jobs.len() - 1)));
+
+                128 + libc::SIGTSTP
+            }
+        }
+    }
+
+    pub fn add_job(&mut self, pid: pid_t, group: Option<ProcessGroup>, command: String) {
+        let job = Job {
+            id: self.next_job_id(),
+            pgid: group.map_or(pid, |group| group.pgid),
+            processes: vec![(pid, State::Running)],
+            command,
+            reported: State::Running,
+        };
+
+        if self.interactive() {
+            self.error(format!("[{}] {}\n", job.id, pid));
+        }
+
+        self.jobs.push(job);
+    }
+
+    pub fn notify_jobs(&mut self) {
+        let mut index = 0;
+
+        while index < self.jobs.len() {
+            self.jobs[index].poll();
+
+            let state = self.jobs[index].state();
+
+            if state != self.jobs[index].reported {
+                if state != State::Running && self.interactive() {
+                    self.error(self.format_job(index));
+                }
+                self.jobs[index].reported = state;
+            }
+
+            if let State::Done(_) = state {
+                self.jobs.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    fn next_job_id(&self) -> usize {
+        self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1
+    }
+
+    fn format_job(&self, index: usize) -> String {
+        let job = &self.jobs[index];
+
+        let mark = if index + 1 == self.jobs.len() {
+            '+'
+        } else if index + 2 == self.jobs.len() {
+            '-'
+        } else {
+            ' '
+        };
+
+        let state = match job.state() {
+            State::Running => String::from("Running"),
+            State::Stopped => String::from("Stopped"),
+            State::Done(status) => match (sys::signal_message(status), sys::exit_code(status)) {
+                (Some(message), _) => message,
+                (None, 0) => String::from("Done"),
+                (None, code) => format!("Exit {}", code),
+            },
+        };
+
+        let suffix = if job.state() == State::Running {
+            " &"
+        } else {
+            ""
+        };
+
+        format!(
+            "[{}]{}  {:<24}{}{}\n",
+            job.id, mark, state, job.command, suffix
+        )
+    }
+
+    fn find_job(&mut self, command: &str, spec: Option<&String>) -> Option<usize> {
+        let index = match spec.map(String::as_str) {
+            None | Some("%") | Some("%%") | Some("%+") => self.jobs.len().checked_sub(1),
+            Some("%-") => self.jobs.len().checked_sub(2),
+            Some(spec) => {
+                let spec = spec.strip_prefix('%').unwrap_or(spec);
+
+                match (spec.parse::<usize>(), spec.strip_prefix('?')) {
+                    (Ok(id), _) => self.jobs.iter().position(|job| job.id == id),
+                    (_, Some(text)) => self.jobs.iter().position(|job| job.command.contains(text)),
+                    _ => self
+                        .jobs
+                        .iter()
+                        .position(|job| job.command.starts_with(spec)),
+                }
+            }
+        };
+
+        if index.is_none() {
+            self.error(format!(
+                "{}: {}: no such job\n",
+                command,
+                spec.map_or("current", String::as_str)
+            ));
+        }
+
+        index
+    }
+
+    pub fn jobs_builtin(&mut self, argv: &[String]) -> i32 {
+        let mut pids = false;
+        let mut long = false;
+        let mut specs = argv;
+
+        while let Some(option) = specs.first().filter(|arg| arg.starts_with('-')) {
+            match option.as_str() {
+                "-l" => long = true,
+                "-p" => pids = true,
+                _ => {
+                    self.error(format!("jobs: {}: invalid option\n", option));
+                    return 2;
+                }
+            }
+            specs = &specs[1..];
+        }
+
+        for job in self.jobs.iter_mut() {
+            job.poll();
+        }
+
+        let indexes: Vec<usize> = if specs.is_empty() {
+            (0..self.jobs.len()).collect()
+        } else {
+            let mut indexes = vec![];
+            for spec in specs.iter() {
+                match self.find_job("jobs", Some(spec)) {
+                    Some(index) => indexes.push(index),
+                    None => return 1,
+                }
+            }
+            indexes
+        };
+
+        for index in indexes.iter().copied() {
+            let job = &self.jobs[index];
+
+            if pids {
+                self.output(format!("{}\n", job.pgid));
+            } else if long {
+                let line = self.format_job(index);
+                let (head, tail) = line.split_at(line.find("  ").unwrap_or(0));
+                self.output(format!("{} {}{}", head, job.pgid, tail));
+            } else {
+                self.output(self.format_job(index));
+            }
+
+            self.jobs[index].reported = self.jobs[index].state();
+        }
+
+        self.jobs
+            .retain(|job| !matches!(job.state(), State::Done(_)));
+
+        0
+    }
+
+    pub fn resume_job(&mut self, command: &str, argv: &[String]) -> i32 {
+        if !self.job_control {
+            self.error(format!("{}: no job control\n", command));
+            return 1;
+        }
+
+        if command == "fg" {
+            let Some(index) = self.find_job(command, argv.first()) else {
+                return 1;
+            };
+
+            let job = self.jobs.remove(index);
+            self.output(format!("{}\n", job.command));
+            return self.wait_job(job, true);
+        }
+
+        let specs: Vec<Option<&String>> = if argv.is_empty() {
+            vec![None]
+        } else {
+            argv.iter().map(Some).collect()
+        };
+
+        for spec in specs {
+            let Some(index) = self.find_job(command, spec) else {
+                return 1;
+            };
+
+            if self.jobs[index].state() == State::Running {
+                self.error(format!(
+                    "bg: job {} already in background\n",
+                    self.jobs[index].id
+                ));
+                continue;
+            }
+
+            self.jobs[index].resume();
+
+            let line = self.format_job(index);
+            let mark = &line[..line.find("  ").unwrap_or(0)];
+            self.output(format!("{} {} &\n", mark, self.jobs[index].command));
+        }
+
+        0
+    }
+
+    pub fn disown(&mut self, argv: &[String]) -> i32 {
+        if argv.first().map(String::as_str) == Some("-a") {
+            self.jobs.clear();
+            return 0;
+        }
+
+        let specs: Vec<Option<&String>> = if argv.is_empty() {
+            vec![None]
+        } else {
+            argv.iter().map(Some).collect()
+        };
+
+        for spec in specs {
+            match self.find_job("disown", spec) {
+                Some(index) => {
+                    self.jobs.remove(index);
+                }
+                None => return 1,
+            }
+        }
+
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::test_shell;
+
+    fn job(id: usize, command: &str) -> Job {
+        Job {
+            id,
+            pgid: 0,
+            processes: vec![],
+            command: String::from(command),
+            reported: State::Running,
+        }
+    }
+
+    #[test]
+    fn finds_jobs_by_spec() {
+        let mut shell = test_shell();
+        shell.jobs = vec![
+            job(1, "sleep 10"),
+            job(3, "vim notes.txt"),
+            job(4, "sleep 20"),
+        ];
+
+        let mut find = |spec: &str| shell.find_job("fg", Some(&String::from(spec)));
+
+        assert_eq!(find("%"), Some(2));
+        assert_eq!(find("%+"), Some(2));
+        assert_eq!(find("%-"), Some(1));
+        assert_eq!(find("%3"), Some(1));
+        assert_eq!(find("1"), Some(0));
+        assert_eq!(find("%?notes"), Some(1));
+        assert_eq!(find("%sleep"), Some(0));
+        assert_eq!(shell.find_job("fg", None), Some(2));
+    }
+}
@@ -0,0 +1,465 @@
+use std::fmt;
+
+use super::ast::{Param, ParamOp, Word, WordPart};
+use super::parser::{self, ParseError};
+
+static OPERATORS: [&str; 19] = [
+    "<<<", "<<-", "&>>", "&&", "||", "<<", ">>", ">&", ">|", "&>", "<&", "<>", ";", "&", "|", "(",
+    ")", ">", "<",
+];
+
+static SPECIAL_PARAMS: &str = "?$#@*!";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(Word),
+    IoNumber(i32),
+    Operator(&'static str),
+    Newline,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{}", word),
+            Token::IoNumber(fd) => write!(f, "{}", fd),
+            Token::Operator(operator) => write!(f, "{}", operator),
+            Token::Newline => write!(f, "newline"),
+            Token::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+pub struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    pub fn new(input: &str) -> Lexer {
+        Lexer {
+            chars: input.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(index, ch)| self.peek_at(index) == Some(ch))
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, ParseError> {
+        self.skip_blanks()?;
+
+        match self.peek() {
+            None => return Ok(Token::Eof),
+            Some('\n') => {
+                self.pos += 1;
+                return Ok(Token::Newline);
+            }
+            _ => {}
+        }
+
+        for operator in OPERATORS.iter() {
+            if self.starts_with(operator) {
+                self.pos += operator.chars().count();
+                return Ok(Token::Operator(operator));
+            }
+        }
+
+        let start = self.pos;
+
+        while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        if self.pos > start && matches!(self.peek(), Some('<') | Some('>')) {
+            let digits: String = self.chars[start..self.pos].iter().collect();
+            if let Ok(fd) = digits.parse() {
+                return Ok(Token::IoNumber(fd));
+            }
+        }
+
+        self.pos = start;
+        self.word().map(Token::Word)
+    }
+
+    fn skip_blanks(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek() {
+                Some(' ') | Some('\t') => self.pos += 1,
+                Some('\\') if self.peek_at(1) == Some('\n') => self.continue_line()?,
+                Some('#') => {
+                    while self.peek().is_some_and(|ch| ch != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn continue_line(&mut self) -> Result<(), ParseError> {
+        self.pos += 2;
+
+        if self.peek().is_none() {
+            return Err(ParseError::Incomplete);
+        }
+
+        Ok(())
+    }
+
+    fn word(&mut self) -> Result<Word, ParseError> {
+        self.word_until(false)
+    }
+
+    fn word_until(&mut self, braces: bool) -> Result<Word, ParseError> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+
+        loop {
+            let ch = match self.peek() {
+                None if braces => return Err(ParseError::Incomplete),
+                None => break,
+                Some(ch) => ch,
+            };
+
+            match ch {
+                '}' if braces => break,
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>' if !braces => break,
+                '$' => match self.dollar()? {
+                    Some(part) => {
+                        Lexer::flush(&mut parts, &mut literal);
+                        parts.push(part);
+                    }
+                    None => {
+                        literal.push(ch);
+                        self.pos += 1;
+                    }
+                },
+                '\\' if self.peek_at(1) == Some('\n') => self.continue_line()?,
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        None => return Err(ParseError::Incomplete),
+                        Some(ch) => {
+                            Lexer::flush(&mut parts, &mut literal);
+                            parts.push(WordPart::Quoted(ch.to_string()));
+                        }
+                    }
+                    self.pos += 1;
+                }
+                '\'' => {
+                    Lexer::flush(&mut parts, &mut literal);
+                    self.pos += 1;
+                    let mut quoted = String::new();
+                    loop {
+                        match self.peek() {
+                            None => return Err(ParseError::Incomplete),
+                            Some('\'') => break,
+                            Some(ch) => quoted.push(ch),
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 1;
+                    parts.push(WordPart::Quoted(quoted));
+                }
+                '"' => {
+                    Lexer::flush(&mut parts, &mut literal);
+                    self.pos += 1;
+                    let quoted = self.double_quoted()?;
+                    parts.push(WordPart::DoubleQuoted(quoted));
+                }
+                '`' => {
+                    Lexer::flush(&mut parts, &mut literal);
+                    parts.push(self.backquoted()?);
+                }
+                _ => {
+                    literal.push(ch);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        Lexer::flush(&mut parts, &mut literal);
+        Ok(Word { parts })
+    }
+
+    fn double_quoted(&mut self) -> Result<Vec<WordPart>, ParseError> {
+        self.quoted_parts(false)
+    }
+
+    fn quoted_parts(&mut self, heredoc: bool) -> Result<Vec<WordPart>, ParseError> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+
+        loop {
+            match self.peek() {
+                None if heredoc => break,
+                None => return Err(ParseError::Incomplete),
+                Some('"') if !heredoc => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        None if heredoc => literal.push('\\'),
+                        None => return Err(ParseError::Incomplete),
+                        Some('\n') => {}
+                        Some('"') if heredoc => literal.push_str("\\\""),
+                        Some(ch @ ('$' | '`' | '"' | '\\')) => {
+                            Lexer::flush(&mut parts, &mut literal);
+                            parts.push(WordPart::Quoted(ch.to_string()));
+                        }
+                        Some(ch) => {
+                            literal.push('\\');
+                            literal.push(ch);
+                        }
+                    }
+                    self.pos += 1;
+                }
+                Some('`') => {
+                    Lexer::flush(&mut parts, &mut literal);
+                    parts.push(self.backquoted()?);
+                }
+                Some('$') => match self.dollar()? {
+                    Some(part) => {
+                        Lexer::flush(&mut parts, &mut literal);
+                        parts.push(part);
+                    }
+                    None => {
+                        literal.push('$');
+                        self.pos += 1;
+                    }
+                },
+                Some(ch) => {
+                    literal.push(ch);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        Lexer::flush(&mut parts, &mut literal);
+        Ok(parts)
+    }
+
+    pub fn heredoc(
+        &mut self,
+        delimiter: &str,
+        strip_tabs: bool,
+        expand: bool,
+    ) -> Result<Word, ParseError> {
+        let start = match self.chars[self.pos..].iter().position(|ch| *ch == '\n') {
+            Some(offset) => self.pos + offset + 1,
+            None => return Err(ParseError::Incomplete),
+        };
+
+        let mut body = String::new();
+        let mut cursor = start;
+
+        loop {
+            if cursor >= self.chars.len() {
+                return Err(ParseError::Incomplete);
+            }
+
+            let end = self.chars[cursor..]
+                .iter()
+                .position(|ch| *ch == '\n')
+                .map_or(self.chars.len(), |offset| cursor + offset);
+
+            let line: String = self.chars[cursor..end].iter().collect();
+            let line = if strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line.as_str()
+            };
+
+            cursor = self.chars.len().min(end + 1);
+
+            if line == delimiter {
+                break;
+            }
+
+            if end == self.chars.len() {
+                return Err(ParseError::Incomplete);
+            }
+
+            body.push_str(line);
+            body.push('\n');
+        }
+
+        self.chars.drain(start..cursor);
+
+        let parts = if expand {
+            Lexer::new(&body).quoted_parts(true)?
+        } else {
+            vec![WordPart::Quoted(body)]
+        };
+
+        Ok(Word {
+            parts: vec![WordPart::DoubleQuoted(parts)],
+        })
+    }
+
+    fn backquoted(&mut self) -> Result<WordPart, ParseError> {
+        let mut text = String::new();
+
+        self.pos += 1;
+
+        loop {
+            match self.peek() {
+                None => return Err(ParseError::Incomplete),
+                Some('`') => break,
+                Some('\\') => match self.peek_at(1) {
+                    Some(ch @ ('$' | '`' | '\\')) => {
+                        text.push(ch);
+                        self.pos += 1;
+                    }
+                    _ => text.push('\\'),
+                },
+                Some(ch) => text.push(ch),
+            }
+            self.pos += 1;
+        }
+
+        self.pos += 1;
+
+        match parser::parse(&text) {
+            Ok(list) => Ok(WordPart::CommandSubst(list, text)),
+            Err(ParseError::Incomplete) => Err(ParseError::Unexpected(String::from("`"))),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn dollar(&mut self) -> Result<Option<WordPart>, ParseError> {
+        let name = match self.peek_at(1) {
+            Some('{') => {
+                self.pos += 2;
+                return self.braced_param().map(Some);
+            }
+            Some('(') => {
+                self.pos += 2;
+                let start = self.pos;
+                let list = parser::parse_substitution(self)?;
+                let text: String = self.chars[start..self.pos - 1].iter().collect();
+                return Ok(Some(WordPart::CommandSubst(list, text)));
+            }
+            Some(ch) if ch == '_' || ch.is_ascii_alphabetic() => {
+                self.pos += 1;
+                self.name()
+            }
+            Some(ch) if SPECIAL_PARAMS.contains(ch) || ch.is_ascii_digit() => {
+                self.pos += 2;
+                ch.to_string()
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(WordPart::Param(Param {
+            name,
+            op: ParamOp::Plain,
+        })))
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+
+        while let Some(ch) = self.peek() {
+            if ch != '_' && !ch.is_ascii_alphanumeric() {
+                break;
+            }
+            name.push(ch);
+            self.pos += 1;
+        }
+
+        name
+    }
+
+    fn braced_param(&mut self) -> Result<WordPart, ParseError> {
+        let start = self.pos;
+        let length = self.peek() == Some('#') && self.peek_at(1).is_some_and(|ch| ch != '}');
+
+        if length {
+            self.pos += 1;
+        }
+
+        let name = match self.peek() {
+            Some(ch) if ch == '_' || ch.is_ascii_alphabetic() => self.name(),
+            Some(ch) if ch.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(ch) = self.peek().filter(|ch| ch.is_ascii_digit()) {
+                    digits.push(ch);
+                    self.pos += 1;
+                }
+                digits
+            }
+            Some(ch) if SPECIAL_PARAMS.contains(ch) => {
+                self.pos += 1;
+                ch.to_string()
+            }
+            _ => String::new(),
+        };
+
+        let colon = self.peek() == Some(':');
+        let op = if colon { self.peek_at(1) } else { self.peek() };
+
+        let op = match op {
+            Some('}') if !colon && !name.is_empty() => {
+                self.pos += 1;
+                return Ok(WordPart::Param(Param {
+                    name,
+                    op: if length {
+                        ParamOp::Length
+                    } else {
+                        ParamOp::Plain
+                    },
+                }));
+            }
+            Some(op @ ('-' | '=' | '?' | '+')) if !length && !name.is_empty() => op,
+            _ => {
+                while self.peek().is_some_and(|ch| ch != '}') {
+                    self.pos += 1;
+                }
+                if self.peek().is_none() {
+                    return Err(ParseError::Incomplete);
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                self.pos += 1;
+                return Err(ParseError::BadSubstitution(format!("${{{}}}", text)));
+            }
+        };
+
+        self.pos += if colon { 2 } else { 1 };
+
+        let word = self.word_until(true)?;
+        self.pos += 1;
+
+        let op = match op {
+            '-' => ParamOp::Default(colon, word),
+            '=' => ParamOp::Assign(colon, word),
+            '?' => ParamOp::Error(colon, word),
+            _ => ParamOp::Alternative(colon, word),
+        };
+
+        Ok(WordPart::Param(Param { name, op }))
+    }
+
+    fn flush(parts: &mut Vec<WordPart>, literal: &mut String) {
+        if !literal.is_empty() {
+            parts.push(WordPart::Literal(std::mem::take(literal)));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use super::editor::{char_width, escape, Key};
+use super::fuzzy;
+use super::Shell;
+
+static QUERY_ITEMS: usize = 100;
+
+pub struct Menu {
+    start: usize,
+    candidates: Vec<String>,
+    names: Vec<String>,
+    pattern: Option<String>,
+    selected: Option<usize>,
+    visible: bool,
+    rows: usize,
+    offset: usize,
+}
+
+impl Menu {
+    pub fn new(start: usize, word: &str, candidates: Vec<String>, fuzzy: bool) -> Menu {
+        let dir = &word[..word.rfind('/').map_or(0, |index| index + 1)];
+
+        let names = candidates
+            .iter()
+            .map(|candidate| {
+                candidate
+                    .strip_prefix(dir)
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or(candidate)
+                    .to_owned()
+            })
+            .collect();
+
+        Menu {
+            start,
+            candidates,
+            names,
+            pattern: fuzzy.then(|| word[dir.len()..].to_owned()),
+            selected: None,
+            visible: false,
+            rows: 1,
+            offset: 0,
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn render(&mut self, width: usize, height: usize) -> Vec<String> {
+        let widths: Vec<usize> = self
+            .names
+            .iter()
+            .map(|name| name.chars().map(char_width).sum())
+            .collect();
+
+        let column_width = widths.iter().max().copied().unwrap_or(0) + 2;
+        let columns = (width / column_width).max(1);
+
+        self.rows = self.names.len().div_ceil(columns).max(1);
+
+        let visible = if self.rows > height {
+            height.saturating_sub(1).max(1)
+        } else {
+            self.rows
+        };
+
+        if let Some(row) = self.selected.map(|selected| selected % self.rows) {
+            if row < self.offset {
+                self.offset = row;
+            } else if row >= self.offset + visible {
+                self.offset = row + 1 - visible;
+            }
+        }
+
+        self.offset = self.offset.min(self.rows - visible);
+
+        let mut lines = vec![];
+
+        for row in self.offset..self.offset + visible {
+            let mut line = String::new();
+
+            for column in 0..columns {
+                let index = column * self.rows + row;
+
+                if index >= self.names.len() {
+                    break;
+                }
+
+                if column > 0 {
+                    let previous = index - self.rows;
+                    line.push_str(&" ".repeat(column_width - widths[previous]));
+                }
+
+                let name = match self
+                    .pattern
+                    .as_ref()
+                    .and_then(|pattern| fuzzy::score(pattern, &self.names[index]))
+                {
+                    Some((_, positions)) => fuzzy::highlight(&self.names[index], &positions),
+                    None => self.names[index].clone(),
+                };
+
+                if Some(index) == self.selected {
+                    line.push_str(&format!("\x1B[7m{}\x1B[0m", name));
+                } else {
+                    line.push_str(&name);
+                }
+            }
+
+            lines.push(line);
+        }
+
+        if visible < self.rows {
+            lines.push(format!(
+                "\x1B[7m-- rows {}-{} of {} --\x1B[0m",
+                self.offset + 1,
+                self.offset + visible,
+                self.rows
+            ));
+        }
+
+        lines
+    }
+}
+
+impl Shell<'_> {
+    pub fn menu_key(&mut self, mut menu: Menu, key: Key) -> Option<bool> {
+        let count = menu.candidates.len();
+
+        let (forward, distance) = match (menu.visible, key) {
+            (false, Key::Tab) => {
+                if count > QUERY_ITEMS && !self.confirm_menu(count)? {
+                    return Some(true);
+                }
+
+                menu.visible = true;
+                self.editor.completion = Some(menu);
+                return Some(true);
+            }
+            (true, Key::Tab | Key::Down) => (true, 1),
+            (true, Key::BackTab | Key::Up) => (false, 1),
+            (true, Key::Right) => (true, menu.rows),
+            (true, Key::Left) => (false, menu.rows),
+            (true, Key::Enter | Key::Escape) if menu.selected.is_some() => {
+                self.editor.completion = None;
+                self.refresh();
+                return Some(true);
+            }
+            (visible, _) => {
+                if visible {
+                    self.editor.completion = None;
+                    self.refresh();
+                }
+                return Some(false);
+            }
+        };
+
+        let selected = match menu.selected {
+            Some(selected) if forward => (selected + distance) % count,
+            Some(selected) => (selected + count - distance % count) % count,
+            None if forward => 0,
+            None => count - 1,
+        };
+
+        let replacement = escape(&menu.candidates[selected]);
+
+        self.editor.buffer.drain(menu.start..self.editor.cursor);
+        self.editor.cursor = menu.start;
+        self.editor.insert(&replacement);
+
+        menu.selected = Some(selected);
+        self.editor.completion = Some(menu);
+
+        Some(true)
+    }
+
+    fn confirm_menu(&mut self, count: usize) -> Option<bool> {
+        let cursor = self.editor.cursor;
+
+        self.editor.cursor = self.editor.buffer.len();
+        self.refresh();
+        self.output(format!("\r\nDisplay all {} possibilities? (y/n)", count));
+
+        let confirmed = matches!(self.read_key()?, Key::Char('y' | 'Y' | ' '));
+
+        self.output("\r\n");
+        self.editor.cursor = cursor;
+        self.editor.row = 0;
+
+        Some(confirmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::test_shell;
+
+    fn menu(start: usize, word: &str, candidates: &[&str]) -> Menu {
+        let candidates = candidates.iter().map(|item| item.to_string()).collect();
+        Menu::new(start, word, candidates, false)
+    }
+
+    #[test]
+    fn renders_columns() {
+        let mut menu = menu(0, "", &["a", "bb", "ccc", "d", "e"]);
+
+        assert_eq!(menu.render(10, 24), vec!["a    d", "bb   e", "ccc"]);
+        assert_eq!(menu.rows, 3);
+        assert_eq!(menu.render(4, 24).len(), 5);
+    }
+
+    #[test]
+    fn scrolls_to_the_selection() {
+        let mut menu = menu(0, "", &["a", "bb", "ccc", "d", "e"]);
+
+        assert_eq!(
+            menu.render(10, 2),
+            vec!["a    d", "\x1B[7m-- rows 1-1 of 3 --\x1B[0m"]
+        );
+
+        menu.selected = Some(2);
+        assert_eq!(
+            menu.render(10, 2),
+            vec!["\x1B[7mccc\x1B[0m", "\x1B[7m-- rows 3-3 of 3 --\x1B[0m"]
+        );
+    }
+
+    #[test]
+    fn cycles_through_candidates() {
+        let mut shell = test_shell();
+        shell.editor.insert("cat src/m");
+
+        let menu = menu(4, "src/m", &["src/main.rs", "src/my file"]);
+        assert_eq!(menu.names, vec!["main.rs", "my file"]);
+        shell.editor.completion = Some(menu);
+
+        let mut press = |key: Key| {
+            let menu = shell.editor.completion.take().unwrap();
+            assert_eq!(shell.menu_key(menu, key), Some(true));
+            shell.editor.buffer.iter().collect::<String>()
+        };
+
+        assert_eq!(press(Key::Tab), "cat src/m");
+        assert_eq!(press(Key::Tab), "cat src/main.rs");
+        assert_eq!(press(Key::Tab), "cat src/my\\ file");
+        assert_eq!(press(Key::Tab), "cat src/main.rs");
+        assert_eq!(press(Key::BackTab), "cat src/my\\ file");
+    }
+}
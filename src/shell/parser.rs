@@ -0,0 +1,729 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::ast::{
+    AndOr, Command, CompoundCommand, Connector, List, Pipeline, Redirect, RedirectKind,
+    SimpleCommand, Word, WordPart,
+};
+use super::lexer::{Lexer, Token};
+
+pub static RESERVED: [&str; 14] = [
+    "if", "then", "elif", "else", "fi", "while", "until", "for", "in", "do", "done", "{", "}", "!",
+];
+
+static TERMINATORS: [&str; 7] = ["then", "elif", "else", "fi", "do", "done", "}"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Incomplete,
+    Unexpected(String),
+    BadSubstitution(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Incomplete => write!(f, "syntax error: unexpected end of file"),
+            ParseError::BadSubstitution(text) => write!(f, "{}: bad substitution", text),
+            ParseError::Unexpected(token) => {
+                write!(f, "syntax error near unexpected token `{}'", token)
+            }
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<List, ParseError> {
+    let mut lexer = Lexer::new(input);
+    let mut parser = Parser {
+        lexer: &mut lexer,
+        peeked: None,
+    };
+
+    let list = parser.list()?;
+
+    match parser.next()? {
+        Token::Eof => Ok(list),
+        token => Err(unexpected(token)),
+    }
+}
+
+pub fn parse_substitution(lexer: &mut Lexer) -> Result<List, ParseError> {
+    let mut parser = Parser {
+        lexer,
+        peeked: None,
+    };
+
+    let list = parser.list()?;
+    parser.expect(")")?;
+
+    Ok(list)
+}
+
+pub fn is_complete(input: &str) -> bool {
+    !matches!(parse(input), Err(ParseError::Incomplete))
+}
+
+fn unexpected(token: Token) -> ParseError {
+    match token {
+        Token::Eof => ParseError::Incomplete,
+        token => ParseError::Unexpected(token.to_string()),
+    }
+}
+
+fn is_reserved(token: &Token, words: &[&str]) -> bool {
+    match token {
+        Token::Word(word) => word.as_literal().is_some_and(|word| words.contains(&word)),
+        _ => false,
+    }
+}
+
+struct Parser<'a> {
+    lexer: &'a mut Lexer,
+    peeked: Option<Token>,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Result<&Token, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token()?);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ParseError> {
+        let token = self.next()?;
+
+        let matched = match &token {
+            Token::Operator(operator) => *operator == expected,
+            token => is_reserved(token, &[expected]),
+        };
+
+        if matched {
+            Ok(())
+        } else {
+            Err(unexpected(token))
+        }
+    }
+
+    fn linebreak(&mut self) -> Result<(), ParseError> {
+        while *self.peek()? == Token::Newline {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    fn list(&mut self) -> Result<List, ParseError> {
+        let mut items = vec![];
+
+        loop {
+            self.linebreak()?;
+
+            let token = self.peek()?;
+
+            if matches!(token, Token::Eof | Token::Operator(")"))
+                || is_reserved(token, &TERMINATORS)
+            {
+                break;
+            }
+
+            items.push(self.and_or()?);
+
+            match self.peek()? {
+                Token::Operator(";") | Token::Newline => {
+                    self.next()?;
+                }
+                Token::Operator("&") => {
+                    self.next()?;
+                    if let Some(item) = items.last_mut() {
+                        item.background = true;
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        Ok(List { items })
+    }
+
+    fn compound_list(&mut self) -> Result<List, ParseError> {
+        let list = self.list()?;
+
+        if list.items.is_empty() {
+            let token = self.next()?;
+            return Err(unexpected(token));
+        }
+
+        Ok(list)
+    }
+
+    fn and_or(&mut self) -> Result<AndOr, ParseError> {
+        let first = self.pipeline()?;
+        let mut rest = vec![];
+
+        loop {
+            let connector = match self.peek()? {
+                Token::Operator("&&") => Connector::And,
+                Token::Operator("||") => Connector::Or,
+                _ => break,
+            };
+
+            self.next()?;
+            self.linebreak()?;
+            rest.push((connector, self.pipeline()?));
+        }
+
+        Ok(AndOr {
+            first,
+            rest,
+            background: false,
+        })
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let bang = is_reserved(self.peek()?, &["!"]);
+
+        if bang {
+            self.next()?;
+        }
+
+        let mut commands = vec![self.command()?];
+
+        while *self.peek()? == Token::Operator("|") {
+            self.next()?;
+            self.linebreak()?;
+            commands.push(self.command()?);
+        }
+
+        Ok(Pipeline { bang, commands })
+    }
+
+    fn command(&mut self) -> Result<Command, ParseError> {
+        let compound = match self.peek()? {
+            Token::Operator("(") => {
+                self.next()?;
+                let list = self.compound_list()?;
+                self.expect(")")?;
+                CompoundCommand::Subshell(list)
+            }
+            Token::Word(word) => match word.as_literal() {
+                Some("{") => {
+                    self.next()?;
+                    let list = self.compound_list()?;
+                    self.expect("}")?;
+                    CompoundCommand::Group(list)
+                }
+                Some("if") => self.if_clause()?,
+                Some("while") => self.loop_clause(false)?,
+                Some("until") => self.loop_clause(true)?,
+                Some("for") => self.for_clause()?,
+                Some(word) if RESERVED.contains(&word) => {
+                    let token = self.next()?;
+                    return Err(unexpected(token));
+                }
+                _ => return self.simple_command(),
+            },
+            _ => return self.simple_command(),
+        };
+
+        let mut redirects = vec![];
+
+        while let Some(redirect) = self.redirect()? {
+            redirects.push(redirect);
+        }
+
+        Ok(Command::Compound(compound, redirects))
+    }
+
+    fn if_clause(&mut self) -> Result<CompoundCommand, ParseError> {
+        let mut branches = vec![];
+        let mut otherwise = None;
+
+        self.next()?;
+
+        loop {
+            let condition = self.compound_list()?;
+            self.expect("then")?;
+            let body = self.compound_list()?;
+            branches.push((condition, body));
+
+            match self.next()? {
+                token if is_reserved(&token, &["elif"]) => continue,
+                token if is_reserved(&token, &["else"]) => {
+                    otherwise = Some(self.compound_list()?);
+                    self.expect("fi")?;
+                    break;
+                }
+                token if is_reserved(&token, &["fi"]) => break,
+                token => return Err(unexpected(token)),
+            }
+        }
+
+        Ok(CompoundCommand::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn loop_clause(&mut self, until: bool) -> Result<CompoundCommand, ParseError> {
+        self.next()?;
+
+        let condition = self.compound_list()?;
+        let body = self.do_group()?;
+
+        Ok(CompoundCommand::Loop {
+            until,
+            condition,
+            body,
+        })
+    }
+
+    fn for_clause(&mut self) -> Result<CompoundCommand, ParseError> {
+        self.next()?;
+
+        let name = match self.next()? {
+            Token::Word(word) if word.as_literal().is_some_and(is_name) => {
+                word.as_literal().unwrap().to_owned()
+            }
+            token => return Err(unexpected(token)),
+        };
+
+        let mut words = None;
+
+        self.linebreak()?;
+
+        if is_reserved(self.peek()?, &["in"]) {
+            self.next()?;
+            let mut list = vec![];
+            while let Token::Word(_) = self.peek()? {
+                if let Token::Word(word) = self.next()? {
+                    list.push(word);
+                }
+            }
+            words = Some(list);
+        }
+
+        if matches!(self.peek()?, Token::Operator(";") | Token::Newline) {
+            self.next()?;
+        }
+
+        let body = self.do_group()?;
+
+        Ok(CompoundCommand::For { name, words, body })
+    }
+
+    fn do_group(&mut self) -> Result<List, ParseError> {
+        self.linebreak()?;
+        self.expect("do")?;
+        let body = self.compound_list()?;
+        self.expect("done")?;
+        Ok(body)
+    }
+
+    fn simple_command(&mut self) -> Result<Command, ParseError> {
+        let mut command = SimpleCommand::default();
+
+        loop {
+            if let Some(redirect) = self.redirect()? {
+                command.redirects.push(redirect);
+                continue;
+            }
+
+            match self.peek()? {
+                Token::Word(_) => {
+                    if let Token::Word(word) = self.next()? {
+                        if command.words.is_empty() {
+                            if let Some(assignment) = assignment(&word) {
+                                command.assignments.push(assignment);
+                                continue;
+                            }
+                        }
+                        command.words.push(word);
+                    }
+                }
+                Token::Operator("(")
+                    if command.words.len() == 1 && command.redirects.is_empty() =>
+                {
+                    return self.function_definition(command.words.pop().unwrap());
+                }
+                _ => break,
+            }
+        }
+
+        if command.words.is_empty()
+            && command.redirects.is_empty()
+            && command.assignments.is_empty()
+        {
+            let token = self.next()?;
+            return Err(unexpected(token));
+        }
+
+        Ok(Command::Simple(command))
+    }
+
+    fn function_definition(&mut self, name: Word) -> Result<Command, ParseError> {
+        let name = match name.as_literal() {
+            Some(name) if is_name(name) => name.to_owned(),
+            _ => return Err(ParseError::Unexpected(String::from("("))),
+        };
+
+        self.next()?;
+        self.expect(")")?;
+        self.linebreak()?;
+
+        match self.command()? {
+            body @ Command::Compound(..) => Ok(Command::Function(name, Rc::new(body))),
+            _ => Err(ParseError::Unexpected(name)),
+        }
+    }
+
+    fn redirect(&mut self) -> Result<Option<Redirect>, ParseError> {
+        let fd = match self.peek()? {
+            Token::IoNumber(fd) => Some(*fd),
+            _ => None,
+        };
+
+        if fd.is_some() {
+            self.next()?;
+        }
+
+        let kind = match self.peek()? {
+            Token::Operator("<") => RedirectKind::Input,
+            Token::Operator("<>") => RedirectKind::ReadWrite,
+            Token::Operator(">") | Token::Operator(">|") => RedirectKind::Output,
+            Token::Operator(">>") => RedirectKind::Append,
+            Token::Operator("&>") if fd.is_none() => RedirectKind::OutputAll,
+            Token::Operator("&>>") if fd.is_none() => RedirectKind::AppendAll,
+            Token::Operator("<&") => RedirectKind::DupInput,
+            Token::Operator(">&") => RedirectKind::DupOutput,
+            Token::Operator("<<<") => RedirectKind::HereString,
+            Token::Operator("<<") | Token::Operator("<<-") => RedirectKind::HereDoc,
+            _ if fd.is_some() => {
+                let token = self.next()?;
+                return Err(unexpected(token));
+            }
+            _ => return Ok(None),
+        };
+
+        let operator = self.next()?;
+
+        let target = match self.next()? {
+            Token::Word(target) => target,
+            token => return Err(unexpected(token)),
+        };
+
+        if kind != RedirectKind::HereDoc {
+            return Ok(Some(Redirect { fd, kind, target }));
+        }
+
+        let target = self.lexer.heredoc(
+            &target.unquoted(),
+            operator == Token::Operator("<<-"),
+            !target.is_quoted(),
+        )?;
+
+        Ok(Some(Redirect { fd, kind, target }))
+    }
+}
+
+fn assignment(word: &Word) -> Option<(String, Word)> {
+    let (name, value) = match word.parts.first() {
+        Some(WordPart::Literal(literal)) => literal.split_once('=')?,
+        _ => return None,
+    };
+
+    if !is_name(name) {
+        return None;
+    }
+
+    let mut parts = vec![];
+
+    if !value.is_empty() {
+        parts.push(WordPart::Literal(value.to_owned()));
+    }
+
+    parts.extend(word.parts.iter().skip(1).cloned());
+
+    Some((name.to_owned(), Word { parts }))
+}
+
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::ast::WordPart;
+    use crate::shell::ast::{Param, ParamOp};
+
+    fn simple(input: &str) -> SimpleCommand {
+        let list = parse(input).unwrap();
+
+        match &list.items[0].first.commands[0] {
+            Command::Simple(command) => command.clone(),
+            command => panic!("not a simple command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn keeps_quoted_operators_in_words() {
+        let command = simple("echo \"a>b\" 'c|d' e\\;f");
+
+        assert!(command.redirects.is_empty());
+        assert_eq!(
+            command.words[1].parts,
+            vec![WordPart::DoubleQuoted(vec![WordPart::Literal(
+                String::from("a>b")
+            )])]
+        );
+        assert_eq!(
+            command.words[2].parts,
+            vec![WordPart::Quoted(String::from("c|d"))]
+        );
+        assert_eq!(
+            command.words[3].parts,
+            vec![
+                WordPart::Literal(String::from("e")),
+                WordPart::Quoted(String::from(";")),
+                WordPart::Literal(String::from("f")),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_lists_and_pipelines() {
+        let list = parse("a | b && c || d; e\nf").unwrap();
+
+        assert_eq!(list.items.len(), 3);
+        assert_eq!(list.items[0].first.commands.len(), 2);
+        assert_eq!(
+            list.items[0]
+                .rest
+                .iter()
+                .map(|(connector, _)| *connector)
+                .collect::<Vec<_>>(),
+            vec![Connector::And, Connector::Or]
+        );
+    }
+
+    #[test]
+    fn parses_output_redirects() {
+        let command = simple("cmd >out 2>>log 2>&1 arg");
+
+        let redirects: Vec<(Option<i32>, RedirectKind, String)> = command
+            .redirects
+            .iter()
+            .map(|redirect| (redirect.fd, redirect.kind, redirect.target.to_string()))
+            .collect();
+
+        assert_eq!(
+            redirects,
+            vec![
+                (None, RedirectKind::Output, String::from("out")),
+                (Some(2), RedirectKind::Append, String::from("log")),
+                (Some(2), RedirectKind::DupOutput, String::from("1")),
+            ]
+        );
+        assert_eq!(command.words.len(), 2);
+    }
+
+    #[test]
+    fn parses_groups_and_subshells() {
+        let list = parse("{ a; b; } >out; (c)").unwrap();
+
+        assert!(matches!(
+            &list.items[0].first.commands[0],
+            Command::Compound(CompoundCommand::Group(body), redirects)
+                if body.items.len() == 2 && redirects.len() == 1
+        ));
+        assert!(matches!(
+            &list.items[1].first.commands[0],
+            Command::Compound(CompoundCommand::Subshell(_), _)
+        ));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert!(matches!(parse("echo >"), Err(ParseError::Incomplete)));
+        assert!(matches!(
+            parse("echo > ;"),
+            Err(ParseError::Unexpected(token)) if token == ";"
+        ));
+        assert!(matches!(parse("a && || b"), Err(ParseError::Unexpected(_))));
+        assert!(matches!(parse("}"), Err(ParseError::Unexpected(_))));
+    }
+
+    #[test]
+    fn waits_for_unterminated_input() {
+        assert!(!is_complete("echo 'open"));
+        assert!(!is_complete("echo \"open"));
+        assert!(!is_complete("a |"));
+        assert!(!is_complete("a &&\n"));
+        assert!(!is_complete("{ a;"));
+        assert!(is_complete("echo done\n"));
+    }
+
+    #[test]
+    fn parses_if_and_loops() {
+        let list = parse("if a; then b; elif c; then d; else e; fi; until f; do g; done").unwrap();
+
+        match &list.items[0].first.commands[0] {
+            Command::Compound(
+                CompoundCommand::If {
+                    branches,
+                    otherwise,
+                },
+                _,
+            ) => {
+                assert_eq!(branches.len(), 2);
+                assert!(otherwise.is_some());
+            }
+            command => panic!("not an if clause: {:?}", command),
+        }
+
+        assert!(matches!(
+            &list.items[1].first.commands[0],
+            Command::Compound(CompoundCommand::Loop { until: true, .. }, _)
+        ));
+        assert!(!is_complete("if true; then"));
+        assert!(!is_complete("while true; do"));
+        assert!(matches!(parse("then"), Err(ParseError::Unexpected(_))));
+    }
+
+    #[test]
+    fn parses_function_definitions() {
+        let list = parse("greet() { echo hi; }").unwrap();
+
+        assert!(matches!(
+            &list.items[0].first.commands[0],
+            Command::Function(name, _) if name == "greet"
+        ));
+        assert!(matches!(
+            parse("1f() { a; }"),
+            Err(ParseError::Unexpected(_))
+        ));
+        assert!(matches!(parse("f() echo"), Err(ParseError::Unexpected(_))));
+    }
+
+    #[test]
+    fn checks_names() {
+        assert!(is_name("_foo1"));
+        assert!(!is_name("1foo"));
+        assert!(!is_name("a-b"));
+        assert!(!is_name(""));
+    }
+
+    #[test]
+    fn splits_assignments_from_words() {
+        let command = simple("A=1 B=\"x y\" cmd C=2");
+
+        let names: Vec<&str> = command
+            .assignments
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["A", "B"]);
+        assert_eq!(
+            command.assignments[1].1.parts,
+            vec![WordPart::DoubleQuoted(vec![WordPart::Literal(
+                String::from("x y")
+            )])]
+        );
+        assert_eq!(command.words.len(), 2);
+    }
+
+    #[test]
+    fn parses_redirect_forms() {
+        let command = simple("cmd <in 2>>log 3<>rw 4>&1 5<&- &>all <<<word");
+
+        let redirects: Vec<(Option<i32>, RedirectKind, String)> = command
+            .redirects
+            .iter()
+            .map(|redirect| (redirect.fd, redirect.kind, redirect.target.to_string()))
+            .collect();
+
+        assert_eq!(
+            redirects,
+            vec![
+                (None, RedirectKind::Input, String::from("in")),
+                (Some(2), RedirectKind::Append, String::from("log")),
+                (Some(3), RedirectKind::ReadWrite, String::from("rw")),
+                (Some(4), RedirectKind::DupOutput, String::from("1")),
+                (Some(5), RedirectKind::DupInput, String::from("-")),
+                (None, RedirectKind::OutputAll, String::from("all")),
+                (None, RedirectKind::HereString, String::from("word")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_here_document_bodies() {
+        let command = simple("cat <<EOF\nhello $name\nEOF\n");
+        let redirect = &command.redirects[0];
+
+        assert_eq!(redirect.kind, RedirectKind::HereDoc);
+        assert_eq!(
+            redirect.target.parts,
+            vec![WordPart::DoubleQuoted(vec![
+                WordPart::Literal(String::from("hello ")),
+                WordPart::Param(Param {
+                    name: String::from("name"),
+                    op: ParamOp::Plain,
+                }),
+                WordPart::Literal(String::from("\n")),
+            ])]
+        );
+
+        let command = simple("cat <<'EOF'\nhello $name\nEOF\n");
+        assert_eq!(command.redirects[0].target.unquoted(), "hello $name\n");
+
+        let command = simple("cat <<-EOF\n\t\tindented\n\tEOF\n");
+        assert_eq!(command.redirects[0].target.unquoted(), "indented\n");
+    }
+
+    #[test]
+    fn waits_for_here_document_bodies() {
+        assert!(!is_complete("cat <<EOF\nbody\n"));
+        assert!(is_complete("cat <<EOF\nbody\nEOF\n"));
+    }
+
+    #[test]
+    fn parses_command_substitution() {
+        let command = simple("echo $(echo a | tr a b) `pwd`");
+
+        let substitutions: Vec<&List> = command.words[1..]
+            .iter()
+            .flat_map(|word| word.parts.iter())
+            .filter_map(|part| match part {
+                WordPart::CommandSubst(list, _) => Some(list),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(substitutions.len(), 2);
+        assert_eq!(substitutions[0].items[0].first.commands.len(), 2);
+        assert!(!is_complete("echo $(ls"));
+    }
+
+    #[test]
+    fn parses_background_lists() {
+        let list = parse("sleep 1 & echo a && echo b &\necho c").unwrap();
+
+        let background: Vec<bool> = list.items.iter().map(|item| item.background).collect();
+        assert_eq!(background, vec![true, true, false]);
+        assert_eq!(list.items[1].rest.len(), 1);
+    }
+}
@@ -0,0 +1,266 @@
+use std::env;
+use std::ffi::CStr;
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{self, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use libc::pid_t;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    unsafe { Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
+}
+
+pub fn read(fd: RawFd, buffer: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len()) } {
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+            -1 => return Err(io::Error::last_os_error()),
+            count => return Ok(count as usize),
+        }
+    }
+}
+
+pub fn poll_input(fd: RawFd, timeout: i32) -> bool {
+    let mut poll = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    unsafe { libc::poll(&mut poll, 1, timeout) > 0 }
+}
+
+pub fn terminal_size() -> (usize, usize) {
+    let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0
+        || size.ws_col == 0
+    {
+        return (80, 24);
+    }
+
+    (size.ws_col as usize, size.ws_row as usize)
+}
+
+pub fn lock(file: &File) -> io::Result<()> {
+    loop {
+        match unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } {
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+            -1 => return Err(io::Error::last_os_error()),
+            _ => return Ok(()),
+        }
+    }
+}
+
+pub fn fork() -> io::Result<pid_t> {
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        pid => Ok(pid),
+    }
+}
+
+pub fn wait(pid: pid_t) -> i32 {
+    match wait_pid(pid, 0) {
+        Some(status) => exit_code(status),
+        None => 1,
+    }
+}
+
+pub fn wait_pid(pid: pid_t, options: i32) -> Option<i32> {
+    let mut status = 0;
+
+    loop {
+        match unsafe { libc::waitpid(pid, &mut status, options) } {
+            0 => return None,
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+            -1 => return None,
+            _ => return Some(status),
+        }
+    }
+}
+
+pub fn exit_code(status: i32) -> i32 {
+    let status = ExitStatus::from_raw(status);
+
+    match status.signal() {
+        Some(signal) => 128 + signal,
+        None => status.code().unwrap_or_default(),
+    }
+}
+
+pub fn signal_message(status: i32) -> Option<String> {
+    if !libc::WIFSIGNALED(status) {
+        return None;
+    }
+
+    let signal = libc::WTERMSIG(status);
+    let description = unsafe { CStr::from_ptr(libc::strsignal(signal)) };
+    let mut message = description.to_string_lossy().into_owned();
+
+    if libc::WCOREDUMP(status) {
+        message.push_str(" (core dumped)");
+    }
+
+    Some(message)
+}
+
+pub fn catch_interrupts() {
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            handle_interrupt as extern "C" fn(i32) as libc::sighandler_t,
+        );
+        libc::signal(libc::SIGQUIT, libc::SIG_IGN);
+    }
+}
+
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::Relaxed)
+}
+
+extern "C" fn handle_interrupt(_: i32) {
+    interrupt();
+}
+
+pub fn reset_signals() {
+    for signal in [
+        libc::SIGINT,
+        libc::SIGQUIT,
+        libc::SIGPIPE,
+        libc::SIGTSTP,
+        libc::SIGTTIN,
+        libc::SIGTTOU,
+    ] {
+        unsafe { libc::signal(signal, libc::SIG_DFL) };
+    }
+}
+
+pub fn temp_file(content: &str) -> io::Result<File> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let path = env::temp_dir().join(format!(
+        "sh-{}-{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+
+    fs::remove_file(&path)?;
+    file.write_all(content.as_bytes())?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(file)
+}
+
+pub struct FdGuard {
+    saved: Vec<(RawFd, Option<OwnedFd>)>,
+}
+
+impl FdGuard {
+    pub fn new() -> FdGuard {
+        FdGuard { saved: vec![] }
+    }
+
+    pub fn save(&mut self, fd: RawFd) -> io::Result<()> {
+        if self.saved.iter().any(|(saved, _)| *saved == fd) {
+            return Ok(());
+        }
+
+        let copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 10) };
+
+        if copy == -1 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() != Some(libc::EBADF) {
+                return Err(error);
+            }
+            self.saved.push((fd, None));
+        } else {
+            self.saved
+                .push((fd, Some(unsafe { OwnedFd::from_raw_fd(copy) })));
+        }
+
+        Ok(())
+    }
+
+    pub fn redirect(&mut self, fd: RawFd, target: impl Into<OwnedFd>) -> io::Result<()> {
+        let target = target.into();
+
+        if target.as_raw_fd() != fd {
+            return self.dup(fd, target.as_raw_fd());
+        }
+
+        self.save(fd)?;
+
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, 0) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let _ = target.into_raw_fd();
+        Ok(())
+    }
+
+    pub fn dup(&mut self, fd: RawFd, target: RawFd) -> io::Result<()> {
+        self.save(fd)?;
+
+        if unsafe { libc::dup2(target, fd) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub fn close(&mut self, fd: RawFd) -> io::Result<()> {
+        self.save(fd)?;
+        unsafe { libc::close(fd) };
+        Ok(())
+    }
+}
+
+impl Drop for FdGuard {
+    fn drop(&mut self) {
+        while let Some((fd, saved)) = self.saved.pop() {
+            match saved {
+                Some(saved) => unsafe { libc::dup2(saved.as_raw_fd(), fd) },
+                None => unsafe { libc::close(fd) },
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_wait_statuses() {
+        assert_eq!(exit_code(3 << 8), 3);
+        assert_eq!(signal_message(3 << 8), None);
+
+        assert_eq!(exit_code(libc::SIGKILL), 128 + libc::SIGKILL);
+        assert_eq!(signal_message(libc::SIGKILL).unwrap(), "Killed");
+        assert_eq!(
+            signal_message(libc::SIGSEGV | 0x80).unwrap(),
+            "Segmentation fault (core dumped)"
+        );
+    }
+}
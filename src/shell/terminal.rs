@@ -0,0 +1,71 @@
+use std::io;
+use std::mem;
+use std::panic;
+use std::sync::OnceLock;
+
+use libc::{pid_t, termios, ECHO, ICANON, IEXTEN, ISIG, IXON, STDIN_FILENO, TCSANOW};
+
+static ORIGINAL: OnceLock<(pid_t, termios)> = OnceLock::new();
+
+static FATAL_SIGNALS: [i32; 5] = [
+    libc::SIGHUP,
+    libc::SIGTERM,
+    libc::SIGABRT,
+    libc::SIGSEGV,
+    libc::SIGBUS,
+];
+
+pub fn init() -> io::Result<()> {
+    let mut original = unsafe { mem::zeroed::<termios>() };
+
+    if unsafe { libc::tcgetattr(STDIN_FILENO, &mut original) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    if ORIGINAL.set((unsafe { libc::getpid() }, original)).is_err() {
+        return Ok(());
+    }
+
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        hook(info);
+    }));
+
+    for signal in FATAL_SIGNALS {
+        unsafe {
+            libc::signal(
+                signal,
+                handle_fatal as extern "C" fn(i32) as libc::sighandler_t,
+            )
+        };
+    }
+
+    Ok(())
+}
+
+pub fn raw_mode() {
+    if let Some((_, original)) = ORIGINAL.get() {
+        let mut raw = *original;
+        raw.c_iflag &= !IXON;
+        raw.c_lflag &= !(ICANON | ECHO | ISIG | IEXTEN);
+        unsafe { libc::tcsetattr(STDIN_FILENO, TCSANOW, &raw) };
+    }
+}
+
+pub fn restore() {
+    if let Some((pid, original)) = ORIGINAL.get() {
+        if unsafe { libc::getpid() } == *pid {
+            unsafe { libc::tcsetattr(STDIN_FILENO, TCSANOW, original) };
+        }
+    }
+}
+
+extern "C" fn handle_fatal(signal: i32) {
+    restore();
+
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
@@ -0,0 +1,192 @@
+use std::env;
+
+use super::Shell;
+
+pub struct SavedVar {
+    name: String,
+    var: Option<String>,
+    env: Option<String>,
+}
+
+impl Shell<'_> {
+    pub fn get_var(&self, name: &str) -> Option<String> {
+        match self.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None => env::var(name).ok(),
+        }
+    }
+
+    pub fn set_var(&mut self, name: &str, value: String) {
+        if self.exported.remove(name) {
+            self.vars.remove(name);
+            env::set_var(name, value);
+        } else if !self.vars.contains_key(name) && env::var_os(name).is_some() {
+            env::set_var(name, value);
+        } else {
+            self.vars.insert(name.to_owned(), value);
+        }
+    }
+
+    pub fn export_var(&mut self, name: &str, value: Option<String>) {
+        match value.or_else(|| self.vars.get(name).cloned()) {
+            Some(value) => {
+                self.vars.remove(name);
+                self.exported.remove(name);
+                env::set_var(name, value);
+            }
+            None if env::var_os(name).is_none() => {
+                self.exported.insert(name.to_owned());
+            }
+            None => {}
+        }
+    }
+
+    pub fn unexport_var(&mut self, name: &str) {
+        self.exported.remove(name);
+
+        if let Ok(value) = env::var(name) {
+            env::remove_var(name);
+            self.vars.insert(name.to_owned(), value);
+        }
+    }
+
+    pub fn unset_var(&mut self, name: &str) {
+        self.vars.remove(name);
+        self.exported.remove(name);
+        env::remove_var(name);
+    }
+
+    pub fn push_temporary_vars(&mut self, vars: Vec<(String, String)>) -> Vec<SavedVar> {
+        vars.into_iter()
+            .map(|(name, value)| {
+                let saved = SavedVar {
+                    var: self.vars.remove(&name),
+                    env: env::var(&name).ok(),
+                    name,
+                };
+                env::set_var(&saved.name, value);
+                saved
+            })
+            .collect()
+    }
+
+    pub fn pop_temporary_vars(&mut self, saved: Vec<SavedVar>) {
+        for saved in saved.into_iter().rev() {
+            match saved.env {
+                Some(value) => env::set_var(&saved.name, value),
+                None => env::remove_var(&saved.name),
+            }
+
+            match saved.var {
+                Some(value) => self.vars.insert(saved.name, value),
+                None => self.vars.remove(&saved.name),
+            };
+        }
+    }
+
+    pub fn get_param(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.status.to_string()),
+            "$" => Some(self.pid.to_string()),
+            "!" => self.last_background.map(|pid| pid.to_string()),
+            "#" => Some((self.args.len() - 1).to_string()),
+            "@" | "*" => {
+                let separator = match name {
+                    "*" => self
+                        .get_var("IFS")
+                        .map_or(Some(' '), |ifs| ifs.chars().next()),
+                    _ => Some(' '),
+                };
+                let mut result = String::new();
+                for (index, arg) in self.args.iter().skip(1).enumerate() {
+                    if index > 0 {
+                        result.extend(separator);
+                    }
+                    result.push_str(arg);
+                }
+                Some(result)
+            }
+            _ if name.chars().all(|ch| ch.is_ascii_digit()) => name
+                .parse()
+                .ok()
+                .and_then(|index: usize| self.args.get(index).cloned()),
+            _ => self.get_var(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::test_shell;
+    use std::env;
+
+    #[test]
+    fn reads_positional_parameters() {
+        let mut shell = test_shell();
+        shell.args = vec![String::from("sh"), String::from("a"), String::from("b")];
+
+        assert_eq!(shell.get_param("#").unwrap(), "2");
+        assert_eq!(shell.get_param("0").unwrap(), "sh");
+        assert_eq!(shell.get_param("2").unwrap(), "b");
+        assert_eq!(shell.get_param("3"), None);
+        assert_eq!(shell.get_param("*").unwrap(), "a b");
+
+        shell.vars.insert(String::from("IFS"), String::from(":"));
+        assert_eq!(shell.get_param("*").unwrap(), "a:b");
+    }
+
+    #[test]
+    fn exports_and_unexports_variables() {
+        let name = "SIMPLE_SHELL_TEST_EXPORT";
+        let mut shell = test_shell();
+
+        shell.set_var(name, String::from("1"));
+        assert!(env::var_os(name).is_none());
+
+        shell.export_var(name, None);
+        assert_eq!(env::var(name).unwrap(), "1");
+
+        shell.set_var(name, String::from("2"));
+        assert_eq!(env::var(name).unwrap(), "2");
+
+        shell.unexport_var(name);
+        assert!(env::var_os(name).is_none());
+        assert_eq!(shell.get_var(name).unwrap(), "2");
+
+        shell.unset_var(name);
+        assert_eq!(shell.get_var(name), None);
+    }
+
+    #[test]
+    fn restores_temporary_variables() {
+        let mut shell = test_shell();
+        shell.set_var("temporary", String::from("old"));
+
+        let saved = shell.push_temporary_vars(vec![
+            (String::from("temporary"), String::from("new")),
+            (String::from("fresh"), String::from("1")),
+        ]);
+        assert_eq!(shell.get_var("temporary").unwrap(), "new");
+        assert_eq!(shell.get_var("fresh").unwrap(), "1");
+
+        shell.pop_temporary_vars(saved);
+        assert_eq!(shell.get_var("temporary").unwrap(), "old");
+        assert_eq!(shell.get_var("fresh"), None);
+    }
+
+    #[test]
+    fn exports_names_without_values() {
+        let name = "SIMPLE_SHELL_TEST_PENDING";
+        let mut shell = test_shell();
+
+        shell.export_var(name, None);
+        assert!(env::var_os(name).is_none());
+
+        shell.set_var(name, String::from("1"));
+        assert_eq!(env::var(name).unwrap(), "1");
+
+        shell.unset_var(name);
+        shell.set_var(name, String::from("2"));
+        assert!(env::var_os(name).is_none());
+    }
+}
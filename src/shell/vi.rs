@@ -0,0 +1,397 @@
+use super::editor::{is_word, Key};
+use super::Shell;
+
+static COUNT_LIMIT: usize = 10000;
+
+struct Motion {
+    target: usize,
+    inclusive: bool,
+}
+
+fn class(ch: char, big: bool) -> u8 {
+    if ch.is_whitespace() {
+        0
+    } else if big || is_word(ch) {
+        1
+    } else {
+        2
+    }
+}
+
+impl Shell<'_> {
+    pub fn vi_escape(&mut self) {
+        self.editor.normal = true;
+
+        if let Some(recording) = self.editor.recording.take() {
+            self.editor.last_change = recording;
+        }
+
+        if self.editor.cursor > self.editor.line_start() {
+            self.editor.cursor -= 1;
+        }
+    }
+
+    pub fn vi_normal(&mut self, first: Key) -> Option<()> {
+        self.editor.recording = (!self.editor.replayed).then(|| vec![first]);
+
+        let (count, key) = self.vi_count(first)?;
+        let count = count.unwrap_or(1);
+        let (start, end) = (self.editor.line_start(), self.editor.line_end());
+        let cursor = self.editor.cursor;
+
+        let change = match key {
+            Key::Char('i') => self.vi_insert(cursor),
+            Key::Char('a') => self.vi_insert((cursor + 1).min(end)),
+            Key::Char('I') => {
+                let target = self.vi_first_non_blank();
+                self.vi_insert(target)
+            }
+            Key::Char('A') => self.vi_insert(end),
+            Key::Char('x') => self.vi_apply('d', cursor, (cursor + count).min(end)),
+            Key::Char('X') => self.vi_apply('d', cursor.saturating_sub(count).max(start), cursor),
+            Key::Char('D') => self.vi_apply('d', cursor, end),
+            Key::Char('C') => self.vi_apply('c', cursor, end),
+            Key::Char('s') => self.vi_apply('c', cursor, (cursor + count).min(end)),
+            Key::Char('S') => self.vi_apply('c', start, end),
+            Key::Char(operator @ ('d' | 'c' | 'y')) => {
+                let first = self.read_key()?;
+                let (motion_count, motion) = self.vi_count(first)?;
+                let count = (count * motion_count.unwrap_or(1)).min(COUNT_LIMIT);
+
+                if motion == Key::Char(operator) {
+                    self.vi_apply(operator, start, end)
+                } else {
+                    match self.vi_motion(motion, count, Some(operator))? {
+                        Some(Motion { target, inclusive }) if target >= cursor => {
+                            let target =
+                                (target + inclusive as usize).min(self.editor.buffer.len());
+                            self.vi_apply(operator, cursor, target)
+                        }
+                        Some(Motion { target, .. }) => self.vi_apply(operator, target, cursor),
+                        None => false,
+                    }
+                }
+            }
+            Key::Char('r') => match self.read_key()? {
+                Key::Char(ch) if cursor + count <= end => {
+                    self.vi_snapshot();
+                    for index in cursor..cursor + count {
+                        self.editor.buffer[index] = ch;
+                    }
+                    self.editor.cursor = cursor + count - 1;
+                    true
+                }
+                _ => false,
+            },
+            Key::Char('~') => {
+                self.vi_snapshot();
+                let last = (cursor + count).min(end);
+                for index in cursor..last {
+                    let ch = self.editor.buffer[index];
+                    self.editor.buffer[index] = if ch.is_uppercase() {
+                        ch.to_lowercase().next().unwrap_or(ch)
+                    } else {
+                        ch.to_uppercase().next().unwrap_or(ch)
+                    };
+                }
+                self.editor.cursor = last;
+                true
+            }
+            Key::Char(put @ ('p' | 'P')) => {
+                if self.editor.register.is_empty() {
+                    false
+                } else {
+                    self.vi_snapshot();
+                    if put == 'p' && cursor < end {
+                        self.editor.cursor += 1;
+                    }
+                    let text = self.editor.register.repeat(count);
+                    self.editor.insert(&text);
+                    self.editor.cursor -= 1;
+                    true
+                }
+            }
+            Key::Char('k') | Key::Up => {
+                self.history_previous();
+                self.editor.cursor = 0;
+                false
+            }
+            Key::Char('j') | Key::Down => {
+                self.history_next();
+                self.editor.cursor = 0;
+                false
+            }
+            Key::Char('u') => {
+                if let Some((buffer, cursor)) = self.editor.undo.pop() {
+                    self.editor.buffer = buffer;
+                    self.editor.cursor = cursor;
+                }
+                false
+            }
+            Key::Char('.') => {
+                let keys = self.editor.last_change.clone();
+                for key in keys.into_iter().rev() {
+                    self.editor.replay.push_front(key);
+                }
+                false
+            }
+            key => {
+                if let Some(motion) = self.vi_motion(key, count, None)? {
+                    self.editor.cursor = motion.target.min(self.editor.buffer.len());
+                }
+                false
+            }
+        };
+
+        if !change {
+            self.editor.recording = None;
+        } else if self.editor.normal {
+            if let Some(recording) = self.editor.recording.take() {
+                self.editor.last_change = recording;
+            }
+        }
+
+        if self.editor.normal {
+            let (start, end) = (self.editor.line_start(), self.editor.line_end());
+            if self.editor.cursor >= end && end > start {
+                self.editor.cursor = end - 1;
+            }
+        }
+
+        Some(())
+    }
+
+    fn vi_count(&mut self, first: Key) -> Option<(Option<usize>, Key)> {
+        let mut key = first;
+        let mut count = None;
+
+        while let Key::Char(ch) = key {
+            match ch.to_digit(10) {
+                Some(digit) if digit > 0 || count.is_some() => {
+                    count = Some((count.unwrap_or(0) * 10 + digit as usize).min(COUNT_LIMIT));
+                    key = self.read_key()?;
+                }
+                _ => break,
+            }
+        }
+
+        Some((count, key))
+    }
+
+    fn vi_snapshot(&mut self) {
+        self.editor
+            .undo
+            .push((self.editor.buffer.clone(), self.editor.cursor));
+    }
+
+    fn vi_insert(&mut self, cursor: usize) -> bool {
+        self.vi_snapshot();
+        self.editor.cursor = cursor;
+        self.editor.normal = false;
+        true
+    }
+
+    fn vi_apply(&mut self, operator: char, start: usize, end: usize) -> bool {
+        self.editor.register = self.editor.buffer[start..end].iter().collect();
+
+        if operator == 'y' {
+            self.editor.cursor = start;
+            return false;
+        }
+
+        self.vi_snapshot();
+        self.editor.buffer.drain(start..end);
+        self.editor.cursor = start;
+
+        if operator == 'c' {
+            self.editor.normal = false;
+        }
+
+        true
+    }
+
+    fn vi_first_non_blank(&self) -> usize {
+        let (start, end) = (self.editor.line_start(), self.editor.line_end());
+
+        (start..end)
+            .find(|index| !self.editor.buffer[*index].is_whitespace())
+            .unwrap_or(end)
+    }
+
+    fn vi_motion(
+        &mut self,
+        key: Key,
+        count: usize,
+        operator: Option<char>,
+    ) -> Option<Option<Motion>> {
+        let (start, end) = (self.editor.line_start(), self.editor.line_end());
+        let cursor = self.editor.cursor;
+
+        let motion = |target: usize, inclusive: bool| Some(Some(Motion { target, inclusive }));
+
+        match key {
+            Key::Char('h') | Key::Left | Key::Backspace => {
+                motion(cursor.saturating_sub(count).max(start), false)
+            }
+            Key::Char('l') | Key::Char(' ') | Key::Right => {
+                motion((cursor + count).min(end), false)
+            }
+            Key::Char('0') | Key::Home => motion(start, false),
+            Key::Char('^') => motion(self.vi_first_non_blank(), false),
+            Key::Char('$') | Key::End => motion(end, false),
+            Key::Char(word @ ('w' | 'W')) => {
+                let big = word == 'W';
+                let on_word = cursor < end && !self.editor.buffer[cursor].is_whitespace();
+
+                if operator == Some('c') && on_word {
+                    let mut target = cursor;
+                    for pass in 0..count {
+                        target = self.vi_word_end(target, big, pass == 0);
+                    }
+                    return motion(target, true);
+                }
+
+                let mut target = cursor;
+                for _ in 0..count {
+                    target = self.vi_next_word(target, big);
+                }
+                motion(target.min(end), false)
+            }
+            Key::Char(word @ ('b' | 'B')) => {
+                let mut target = cursor;
+                for _ in 0..count {
+                    target = self.vi_previous_word(target, word == 'B');
+                }
+                motion(target.max(start), false)
+            }
+            Key::Char(word @ ('e' | 'E')) => {
+                let mut target = cursor;
+                for _ in 0..count {
+                    target = self.vi_word_end(target, word == 'E', false);
+                }
+                motion(target.min(end.saturating_sub(1)).max(start), true)
+            }
+            Key::Char(kind @ ('f' | 'F' | 't' | 'T')) => match self.read_key()? {
+                Key::Char(ch) => {
+                    self.editor.last_find = Some((kind, ch));
+                    Some(self.vi_find(kind, ch, count))
+                }
+                _ => Some(None),
+            },
+            Key::Char(repeat @ (';' | ',')) => match self.editor.last_find {
+                Some((kind, ch)) => {
+                    let kind = if repeat == ',' {
+                        match kind {
+                            'f' => 'F',
+                            'F' => 'f',
+                            't' => 'T',
+                            _ => 't',
+                        }
+                    } else {
+                        kind
+                    };
+                    Some(self.vi_find(kind, ch, count))
+                }
+                None => Some(None),
+            },
+            _ => Some(None),
+        }
+    }
+
+    fn vi_find(&self, kind: char, ch: char, count: usize) -> Option<Motion> {
+        let (start, end) = (self.editor.line_start(), self.editor.line_end());
+        let buffer = &self.editor.buffer;
+        let mut target = self.editor.cursor;
+
+        for _ in 0..count {
+            target = match kind {
+                'f' | 't' => {
+                    let from = if kind == 't' { target + 2 } else { target + 1 };
+                    let found = (from.min(end)..end).find(|index| buffer[*index] == ch)?;
+                    if kind == 't' {
+                        found - 1
+                    } else {
+                        found
+                    }
+                }
+                _ => {
+                    let to = if kind == 'T' {
+                        target.saturating_sub(1)
+                    } else {
+                        target
+                    };
+                    let found = (start..to.max(start))
+                        .rev()
+                        .find(|index| buffer[*index] == ch)?;
+                    if kind == 'T' {
+                        found + 1
+                    } else {
+                        found
+                    }
+                }
+            };
+        }
+
+        Some(Motion {
+            target,
+            inclusive: matches!(kind, 'f' | 't'),
+        })
+    }
+
+    fn vi_next_word(&self, from: usize, big: bool) -> usize {
+        let buffer = &self.editor.buffer;
+        let mut index = from;
+
+        if index < buffer.len() {
+            let current = class(buffer[index], big);
+            while index < buffer.len() && current != 0 && class(buffer[index], big) == current {
+                index += 1;
+            }
+        }
+
+        while index < buffer.len() && buffer[index].is_whitespace() && buffer[index] != '\n' {
+            index += 1;
+        }
+
+        index
+    }
+
+    fn vi_previous_word(&self, from: usize, big: bool) -> usize {
+        let buffer = &self.editor.buffer;
+        let mut index = from;
+
+        while index > 0 && buffer[index - 1].is_whitespace() {
+            index -= 1;
+        }
+
+        if index > 0 {
+            let current = class(buffer[index - 1], big);
+            while index > 0 && class(buffer[index - 1], big) == current {
+                index -= 1;
+            }
+        }
+
+        index
+    }
+
+    fn vi_word_end(&self, from: usize, big: bool, stay: bool) -> usize {
+        let buffer = &self.editor.buffer;
+        let mut index = if stay { from } else { from + 1 };
+
+        while index < buffer.len() && buffer[index].is_whitespace() {
+            index += 1;
+        }
+
+        if index >= buffer.len() {
+            return buffer.len().saturating_sub(1);
+        }
+
+        let current = class(buffer[index], big);
+
+        while index + 1 < buffer.len() && class(buffer[index + 1], big) == current {
+            index += 1;
+        }
+
+        index
+    }
+}